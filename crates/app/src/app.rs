@@ -1,6 +1,16 @@
+use crate::bulk::{BulkAction, BulkCommand};
+use crate::detail::TaskField;
+use crate::external::LineChange;
+use crate::hierarchy::Hierarchy;
+use crate::pomodoro::{Phase, Pomodoro};
+use crate::report::Report;
 use crate::selection_list::SelectionList;
+use crate::timelog::{Session, TimeReport, TimeSpan};
+use chrono::Datelike;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::Write;
 use std::str::FromStr;
+use todo_txt::tasks::TaskCounts;
 
 //------------------------------------------------------------------------------
 //
@@ -14,9 +24,37 @@ pub enum Mode {
     Edit,
     Normal,
     Help,
+    Report,
+    Detail,
+    /// Waiting for a priority letter for the selected task
+    Priority,
+    /// Choosing a due date for the selected task
+    DatePicker,
+    /// Adding or removing existing contexts or projects on the selected task
+    Pick(PickKind),
+    /// Choosing a deleted task to restore
+    Trash,
+    /// Choosing tasks the selected task depends on
+    Link,
+    /// Showing the tasks the selected task depends on, and its dependents
+    Dependencies,
+    /// Choosing the parent of the selected task
+    Parent,
+    /// Showing time spent per project and context
+    TimeReport,
+    /// Choosing a command to apply to all marked tasks, then (for commands
+    /// which need one) choosing its argument
+    Bulk(Option<BulkCommand>),
     Confirm(ConfirmedAction),
 }
 
+/// Used to specify which names are listed in [Mode::Pick]
+#[derive(PartialEq, Copy, Clone)]
+pub enum PickKind {
+    Context,
+    Project,
+}
+
 /// Used to specify which list is currently under focus
 #[derive(PartialEq, Copy, Clone)]
 pub enum Focus {
@@ -26,34 +64,192 @@ pub enum Focus {
     Priorities,
 }
 
+/// Used to specify what the text entered in edit mode replaces
+#[derive(PartialEq, Copy, Clone)]
+pub enum EditTarget {
+    /// The whole selected task, or a new task if `[new]` is selected
+    Task,
+    /// A single field of the selected task
+    Field(TaskField),
+    /// A new task added with the capture defaults, keeping the selection
+    Capture,
+}
+
 /// Actions requiring user confirmation
 #[derive(PartialEq, Copy, Clone)]
 pub enum ConfirmedAction {
     Save,
     Sort,
+    /// Delete the selected task
+    Delete,
+    /// Apply the pending [BulkAction] to all marked tasks
+    Bulk,
+}
+
+/// Event from outside of the user's input, delivered to [App::handle_event]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AppEvent {
+    /// Sent periodically so timers, the clock and date dependent state can be
+    /// updated
+    Tick,
+    /// The source file at the given index into [App::sources] was modified at
+    /// the given time
+    SourceModified(usize, std::time::SystemTime),
 }
 
+/// Changes made by a modification, recorded as it is made so that it can be
+/// undone without copying the whole task list.  A modification changes tasks in
+/// place, then removes tasks, then adds tasks at the end, or only sorts them.
+/// Indices are into `tasks` as it was before the modification.
+struct UndoEntry {
+    /// Number of tasks before the modification
+    len: usize,
+    /// Tasks changed in place or moved to another source, with their previous
+    /// values and sources
+    changed: HashMap<usize, (todo_txt::task::Task, usize)>,
+    /// Tasks removed to the trash, in order, with their sources
+    removed: Vec<(usize, todo_txt::task::Task, usize)>,
+    /// Previous index of each task, if the tasks were sorted
+    order: Option<Vec<usize>>,
+    /// Marks, if the modification moved or removed tasks
+    marked: Option<HashSet<usize>>,
+    /// Length of the trash, and the entry restored from it if any
+    trash_len: usize,
+    restored: Option<(usize, (todo_txt::task::Task, usize))>,
+    /// The running timer and pomodoro, whose task indices change along with
+    /// the tasks
+    timer: Option<(usize, chrono::NaiveDateTime)>,
+    pomodoro: Option<Pomodoro>,
+    /// Number of timed sessions, so undoing stopping a timer drops its session
+    sessions: usize,
+}
+
+/// Maximum number of modifications which can be undone
+pub const UNDO_LIMIT: usize = 100;
+
 pub struct App<'a> {
     pub title: &'a str,
-    pub filepath: &'a str,
     should_quit: bool,
 
+    /// Paths of all todo.txt files shown in the aggregate view.  New tasks are
+    /// added to the first source.
+    sources: Vec<&'a str>,
+    /// Line endings and such of each source file, kept when saving (parallel
+    /// to `sources`)
+    source_formats: Vec<todo_txt::file::FileFormat>,
+
     tasks: Vec<todo_txt::task::Task>,
+    /// Display strings of `tasks`, refreshed along with the counts rather than
+    /// formatted every frame
+    task_strings: Vec<String>,
+    /// The only task modified since the counts were last updated, if known, so
+    /// just its display string and base filter entry are refreshed
+    modified_task: Option<usize>,
+    /// Index into `sources` of the file each task was read from (parallel to `tasks`)
+    task_sources: Vec<usize>,
 
     task_list: SelectionList<usize>,
+    /// Tasks passing the filters which don't depend on the selected context,
+    /// project or priority, indexed by them so changing those selections only
+    /// visits the tasks selected
+    filter_base: Option<crate::filter::FilterIndex>,
+    /// Indices into `tasks` of tasks marked for bulk commands
+    marked: HashSet<usize>,
+    bulk_list: SelectionList<BulkCommand>,
+    bulk_action: Option<BulkAction>,
+    undo_stack: VecDeque<UndoEntry>,
+    /// Indices into `tasks` of tasks being edited in an external editor
+    external_edit: Vec<usize>,
+    /// Tasks deleted this session, with their sources, oldest first
+    trash: Vec<(todo_txt::task::Task, usize)>,
+    /// File deleted tasks are appended to when saving, if any
+    trash_file: Option<&'a str>,
+    /// Tasks deleted since `trash_file` was last appended to.  Unlike `trash`,
+    /// this is not rolled back by undo or restoring, so every deletion is saved.
+    trash_unsaved: Vec<todo_txt::task::Task>,
+    /// Index into `tasks` of the task being timed, and when timing started
+    timer: Option<(usize, chrono::NaiveDateTime)>,
+    /// Sessions timed this run, oldest first
+    sessions: Vec<Session>,
+    /// File timed sessions are appended to when saving, if any
+    time_log: Option<&'a str>,
+    /// Number of sessions at the start of `sessions` already appended to
+    /// `time_log`
+    sessions_saved: usize,
+    pomodoro: Option<Pomodoro>,
+    /// Work periods of `pomodoro` which ended while the user was editing, to be
+    /// counted on its task once editing is done
+    pomodoros_unrecorded: u32,
+    /// Lengths of pomodoro work periods and breaks
+    pomodoro_lengths: (chrono::Duration, chrono::Duration),
 
     context_list: SelectionList<String>,
     project_list: SelectionList<String>,
     priority_list: SelectionList<String>,
 
+    context_tree: Hierarchy,
+    project_tree: Hierarchy,
+
+    task_counts: TaskCounts,
+    context_counts: HashMap<String, TaskCounts>,
+    project_counts: HashMap<String, TaskCounts>,
+    priority_counts: HashMap<char, TaskCounts>,
+    /// Indices into `tasks` of open tasks depending on open tasks
+    blocked: HashSet<usize>,
+    hide_blocked: bool,
+    /// Index into `tasks` of the task whose dependencies or parent are being
+    /// chosen, and indices of the tasks listed in the pick list to choose from
+    link_task: usize,
+    link_candidates: Vec<usize>,
+    /// Show subtasks beneath their parents in the task list
+    outline: bool,
+    /// Depth in the outline of each item of `task_list`
+    task_depths: Vec<usize>,
+    /// Ids of tasks with subtasks
+    parent_ids: HashSet<String>,
+    /// Ids of tasks whose subtasks are hidden in the outline
+    collapsed_tasks: HashSet<String>,
+    /// Complete open subtasks along with their parent
+    complete_subtasks: bool,
+
     mode: Mode,
     focus: Focus,
+    edit_target: EditTarget,
+
+    show_detail: bool,
+    field_list: SelectionList<TaskField>,
+
+    picker_date: chrono::NaiveDate,
+    pick_list: SelectionList<String>,
 
     omit_completed: bool,
+    /// Show only tasks without contexts or projects other than the capture
+    /// defaults
+    inbox_only: bool,
+    /// Words added to captured tasks, eg, `+inbox`
+    capture_default: String,
+    week_start: chrono::Weekday,
+    report: Option<Report>,
+    /// Sessions read from the time log in time report mode, and the period
+    /// reported
+    logged_sessions: Vec<Session>,
+    time_span: TimeSpan,
+    time_report_date: chrono::NaiveDate,
+    time_report: Option<TimeReport>,
+    /// Date of the most recent tick, to notice when the day changes
+    today: chrono::NaiveDate,
+    /// When the source files were last written by [App::save]
+    saved_at: Option<std::time::SystemTime>,
+    /// Sources modified by another program since the app last saved, whose
+    /// changes saving would overwrite
+    modified_sources: Vec<usize>,
     error_msg: String,
     frame_time: f64,
 }
 
+/// Tag keys offered for completion even if no task uses them yet
+pub static WELL_KNOWN_TAG_KEYS: [&str; 2] = ["due", "t"];
+
 pub static ALL_TOKEN: &str = "[all]";
 pub static NEW_TOKEN: &str = "[new]";
 
@@ -65,20 +261,80 @@ impl<'a> App<'a> {
         ]
         .concat();
 
-        App {
+        let context_tree = Hierarchy::new(&todo_txt::tasks::collect_contexts(&tasks, true));
+        let project_tree = Hierarchy::new(&todo_txt::tasks::collect_projects(&tasks, true));
+
+        let mut app = App {
             title,
-            filepath,
             should_quit: false,
 
+            sources: vec![filepath],
+            source_formats: vec![todo_txt::file::FileFormat::default()],
+            task_sources: vec![0; tasks.len()],
+
             task_list: SelectionList::with_items(App::get_task_items(&tasks)),
-            context_list: SelectionList::with_items(App::get_context_items(&tasks)),
-            project_list: SelectionList::with_items(App::get_project_items(&tasks)),
+            filter_base: None,
+            task_strings: Vec::new(),
+            modified_task: None,
+            marked: HashSet::new(),
+            bulk_list: SelectionList::with_items(BulkCommand::ALL.to_vec()),
+            bulk_action: None,
+            undo_stack: VecDeque::new(),
+            external_edit: Vec::new(),
+            trash: Vec::new(),
+            trash_file: None,
+            trash_unsaved: Vec::new(),
+            timer: None,
+            sessions: Vec::new(),
+            time_log: None,
+            sessions_saved: 0,
+            pomodoro: None,
+            pomodoros_unrecorded: 0,
+            pomodoro_lengths: (chrono::Duration::minutes(25), chrono::Duration::minutes(5)),
+
+            context_list: SelectionList::with_items(App::get_hierarchy_items(&context_tree)),
+            project_list: SelectionList::with_items(App::get_hierarchy_items(&project_tree)),
             priority_list: SelectionList::with_items(App::get_priority_items(&tasks)),
 
+            context_tree,
+            project_tree,
+
+            task_counts: TaskCounts::default(),
+            context_counts: HashMap::new(),
+            project_counts: HashMap::new(),
+            priority_counts: HashMap::new(),
+            blocked: HashSet::new(),
+            hide_blocked: false,
+            link_task: 0,
+            link_candidates: Vec::new(),
+            outline: false,
+            task_depths: Vec::new(),
+            parent_ids: HashSet::new(),
+            collapsed_tasks: HashSet::new(),
+            complete_subtasks: false,
+
             mode: Mode::Normal,
             focus: Focus::Tasks,
+            edit_target: EditTarget::Task,
+
+            show_detail: false,
+            field_list: SelectionList::with_items(TaskField::ALL.to_vec()),
+
+            picker_date: chrono::Local::now().date_naive(),
+            pick_list: SelectionList::with_items(Vec::new()),
 
             omit_completed: false,
+            inbox_only: false,
+            capture_default: String::new(),
+            week_start: chrono::Weekday::Mon,
+            report: None,
+            logged_sessions: Vec::new(),
+            time_span: TimeSpan::Week,
+            time_report_date: chrono::Local::now().date_naive(),
+            time_report: None,
+            today: chrono::Local::now().date_naive(),
+            saved_at: None,
+            modified_sources: Vec::new(),
 
             //input: tui_input::Input::new("".to_string()),
             error_msg: "".to_string(),
@@ -86,7 +342,9 @@ impl<'a> App<'a> {
             frame_time: 0f64,
 
             tasks, // NB: at end since it consumes local task object
-        }
+        };
+        app.update_counts();
+        app
     }
 
     pub fn quit(&mut self) {
@@ -97,121 +355,1487 @@ impl<'a> App<'a> {
         self.should_quit
     }
 
+    /// Add the tasks of another todo.txt file to the aggregate view
+    pub fn add_source(&mut self, filepath: &'a str, tasks: &[todo_txt::task::Task]) {
+        let source = self.sources.len();
+        self.sources.push(filepath);
+        self.source_formats
+            .push(todo_txt::file::FileFormat::default());
+        self.tasks.extend_from_slice(tasks);
+        self.task_sources.extend(vec![source; tasks.len()]);
+        self.update_state_after_edit();
+    }
+
+    /// Set the first day of the week, used for relative dates, the date picker
+    /// and weekly reports
+    pub fn set_week_start(&mut self, week_start: chrono::Weekday) {
+        self.week_start = week_start;
+    }
+
+    pub fn week_start(&self) -> chrono::Weekday {
+        self.week_start
+    }
+
+    /// Set the words, eg, a default context or project, added to captured tasks
+    pub fn set_capture_default(&mut self, capture_default: &str) {
+        self.capture_default = capture_default.to_string();
+        self.filter_base = None;
+    }
+
+    pub fn capture_default(&self) -> &str {
+        &self.capture_default
+    }
+
     pub fn tasks(&self) -> &Vec<todo_txt::task::Task> {
         &self.tasks
     }
 
+    pub fn sources(&self) -> &[&'a str] {
+        &self.sources
+    }
+
+    /// Path of the first source file, which new tasks are added to
+    pub fn filepath(&self) -> &'a str {
+        self.sources[0]
+    }
+
+    /// Set the format the source file at index `source` is saved in, eg, the
+    /// format it was read in
+    pub fn set_source_format(&mut self, source: usize, format: todo_txt::file::FileFormat) {
+        self.source_formats[source] = format;
+    }
+
+    /// Index into [sources] of the file the task at `task_idx` belongs to
+    pub fn task_source(&self, task_idx: usize) -> usize {
+        self.task_sources[task_idx]
+    }
+
+    /// Short display name of a source file: its file name without directories,
+    /// or with its parent directory if another source has the same file name
+    pub fn source_name(&self, source: usize) -> &'a str {
+        let file_name = |path: &'a str| {
+            std::path::Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(path)
+        };
+        let path = self.sources[source];
+        let name = file_name(path);
+        let clash = self
+            .sources
+            .iter()
+            .enumerate()
+            .any(|(other, other_path)| other != source && file_name(other_path) == name);
+        if !clash || !path.ends_with(name) {
+            return name;
+        }
+        let parent = path[..path.len() - name.len()].trim_end_matches(std::path::is_separator);
+        let start = parent
+            .rfind(std::path::is_separator)
+            .map_or(0, |sep| sep + 1);
+        &path[start..]
+    }
+
     pub fn context_list(&self) -> &SelectionList<String> {
         &self.context_list
     }
 
-    pub fn project_list(&self) -> &SelectionList<String> {
-        &self.project_list
-    }
-    
-    pub fn priority_list(&self) -> &SelectionList<String> {
-        &self.priority_list
+    pub fn project_list(&self) -> &SelectionList<String> {
+        &self.project_list
+    }
+    
+    pub fn priority_list(&self) -> &SelectionList<String> {
+        &self.priority_list
+    }
+
+    /// Display string for an item of the context list, indented by nesting depth
+    pub fn context_label(&self, item: &str) -> String {
+        if item == ALL_TOKEN {
+            item.to_string()
+        } else {
+            self.context_tree.label(item)
+        }
+    }
+
+    /// Display string for an item of the project list, indented by nesting depth
+    pub fn project_label(&self, item: &str) -> String {
+        if item == ALL_TOKEN {
+            item.to_string()
+        } else {
+            self.project_tree.label(item)
+        }
+    }
+
+    /// Keys of all tags used by any task, along with [WELL_KNOWN_TAG_KEYS]
+    pub fn tag_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = WELL_KNOWN_TAG_KEYS.iter().map(|k| k.to_string()).collect();
+        for task in &self.tasks {
+            for (key, _) in task.tags() {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Completion candidates for a partially typed context, project or tag key.
+    /// See [crate::completion::complete]
+    pub fn completions(&self, word: &str) -> Vec<String> {
+        crate::completion::complete(
+            word,
+            self.context_tree.names(),
+            self.project_tree.names(),
+            &self.tag_keys(),
+        )
+    }
+
+    /// Open, completed and overdue counts over all tasks
+    pub fn task_counts(&self) -> TaskCounts {
+        self.task_counts
+    }
+
+    /// Task counts for an item of the context list, including nested contexts
+    pub fn context_counts(&self, item: &str) -> TaskCounts {
+        if item == ALL_TOKEN {
+            self.task_counts
+        } else {
+            self.context_counts.get(item).copied().unwrap_or_default()
+        }
+    }
+
+    /// Task counts for an item of the project list, including nested projects
+    pub fn project_counts(&self, item: &str) -> TaskCounts {
+        if item == ALL_TOKEN {
+            self.task_counts
+        } else {
+            self.project_counts.get(item).copied().unwrap_or_default()
+        }
+    }
+
+    /// Task counts for an item of the priority list
+    pub fn priority_counts(&self, item: &str) -> TaskCounts {
+        match item.chars().next() {
+            _ if item == ALL_TOKEN => self.task_counts,
+            Some(priority) => self
+                .priority_counts
+                .get(&priority)
+                .copied()
+                .unwrap_or_default(),
+            None => TaskCounts::default(),
+        }
+    }
+
+    /// Recompute cached task counts after tasks are modified
+    fn update_counts(&mut self) {
+        let today = self.today;
+        let tasks = &self.tasks[1..];
+        self.task_counts = todo_txt::tasks::count_tasks(tasks, today);
+        self.context_counts = todo_txt::tasks::count_contexts(tasks, today);
+        self.project_counts = todo_txt::tasks::count_projects(tasks, today);
+        self.priority_counts = todo_txt::tasks::count_priorities(tasks, today);
+        let blocked = todo_txt::tasks::blocked(&self.tasks);
+        let blocked_changed = blocked != self.blocked;
+        self.blocked = blocked;
+        self.parent_ids = self
+            .tasks
+            .iter()
+            .filter_map(|task| task.parent())
+            .map(String::from)
+            .collect();
+
+        match self.modified_task.take() {
+            Some(idx) if self.task_strings.len() == self.tasks.len() => {
+                self.task_strings[idx] = self.tasks[idx].to_string();
+                if self.hide_blocked && blocked_changed {
+                    // The modification may have blocked or unblocked others
+                    self.filter_base = None;
+                } else {
+                    self.refilter_base(idx);
+                }
+            }
+            _ => {
+                self.task_strings = self.tasks.iter().map(|task| task.to_string()).collect();
+                self.filter_base = None;
+            }
+        }
+    }
+
+    /// Reindex the task at `idx` in the cached base filter, or drop it if it no
+    /// longer passes
+    fn refilter_base(&mut self, idx: usize) {
+        let passes = !self.base_filter(vec![idx]).is_empty();
+        let Some(base) = self.filter_base.as_mut() else {
+            return;
+        };
+        base.remove(idx);
+        if passes {
+            base.insert(idx, &self.tasks[idx]);
+        }
+    }
+
+    /// The tasks among `indices` into `tasks` passing the filters which don't
+    /// depend on the side panes, ie, the view toggles
+    fn base_filter(&self, indices: Vec<usize>) -> Vec<usize> {
+        let inbox_names = crate::capture::inbox_names(&self.capture_default);
+        todo_txt::tasks::TasksFilter::with_indices(
+            &self.tasks[1..],
+            indices.iter().map(|i| i - 1).collect(),
+        )
+        .without_completed(self.omit_completed)
+        .with_only(
+            self.inbox_only
+                .then_some((inbox_names.0.as_slice(), inbox_names.1.as_slice())),
+        )
+        .task_indices
+        .iter()
+        .map(|i| i + 1)
+        .filter(|i| !(self.hide_blocked && self.blocked.contains(i)))
+        .collect()
+    }
+
+    /// Display string of the task at `task_idx`
+    pub fn task_string(&self, task_idx: usize) -> &str {
+        &self.task_strings[task_idx]
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+    
+    pub fn task_list(&self) -> &SelectionList<usize> {
+        &self.task_list
+    }
+
+    /// Create list of items for display, including ALL_TOKEN or NEW_TOKEN header
+    pub fn get_task_items(tasks: &[todo_txt::task::Task]) -> Vec<usize> {
+        // tasks already contains NEW_TOKEN task
+        Vec::from_iter(0..tasks.len())
+    }
+
+    /// See [get_task_items].  Used for contexts and projects, which may be
+    /// nested.  Names hidden beneath collapsed nodes are omitted.
+    pub fn get_hierarchy_items(tree: &Hierarchy) -> Vec<String> {
+        [vec![ALL_TOKEN.to_string()], tree.visible()].concat()
+    }
+
+    /// See [get_task_items]
+    pub fn get_priority_items(tasks: &[todo_txt::task::Task]) -> Vec<String> {
+        [
+            vec![ALL_TOKEN.to_string()],
+            todo_txt::tasks::collect_priorities(tasks, true)
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        ]
+        .concat()
+    }
+
+    fn get_selected_item_or_first<T: Clone>(list: &SelectionList<T>) -> Option<T> {
+        list.items().get(list.selection().unwrap_or(0)).cloned()
+    }
+
+    fn get_selected_item<T: Clone>(list: &SelectionList<T>) -> Option<T> {
+        match list.selection() {
+            Some(i) if i > 0 => list.items().get(i).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Write every task back to the file it was read from
+    pub fn save(&mut self) -> std::io::Result<()> {
+        for (source, filepath) in self.sources.iter().enumerate() {
+            let file = std::fs::File::create(filepath)?;
+            let mut writer = todo_txt::file::TaskWriter::new(
+                std::io::BufWriter::new(file),
+                self.source_formats[source],
+            );
+            for (task, _) in self.tasks[1..]
+                .iter()
+                .zip(&self.task_sources[1..])
+                .filter(|(_, task_source)| **task_source == source)
+            {
+                writer.write_task(task)?;
+            }
+            writer.finish()?;
+        }
+
+        if let Some(trash_file) = self.trash_file {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(trash_file)?;
+            for task in &self.trash_unsaved {
+                writeln!(&mut file, "{}", task)?;
+            }
+            self.trash_unsaved.clear();
+        }
+
+        if let Some(time_log) = self.time_log {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(time_log)?;
+            for session in &self.sessions[self.sessions_saved..] {
+                writeln!(&mut file, "{}", session)?;
+            }
+            self.sessions_saved = self.sessions.len();
+        }
+        self.saved_at = Some(std::time::SystemTime::now());
+        self.modified_sources.clear();
+        Ok(())
+    }
+
+    /// Sources modified by another program since they were last saved, whose
+    /// changes saving would overwrite
+    pub fn modified_sources(&self) -> &[usize] {
+        &self.modified_sources
+    }
+
+    /// Today's date, as of the most recent tick
+    pub fn today(&self) -> chrono::NaiveDate {
+        self.today
+    }
+
+    pub fn start_frame(&mut self) {}
+
+    /// Message describing the most recent failed operation, if any
+    pub fn error_msg(&self) -> &str {
+        &self.error_msg
+    }
+
+    pub fn set_error(&mut self, msg: &str) {
+        self.error_msg = msg.to_string();
+    }
+
+    pub fn clear_error(&mut self) {
+        self.error_msg.clear();
+    }
+
+    pub fn end_frame(&mut self, frame_time: f64) {
+        self.frame_time = frame_time;
+    }
+
+    pub fn sort_tasks(&mut self) {
+        self.push_undo();
+
+        // Keep the [new] task at the head and each task paired with its source,
+        // remembering where each task moves to
+        let mut tasks_with_sources: Vec<(todo_txt::task::Task, usize, usize)> = self
+            .tasks
+            .drain(1..)
+            .zip(self.task_sources.drain(1..))
+            .enumerate()
+            .map(|(i, (task, source))| (task, source, i + 1))
+            .collect();
+        tasks_with_sources.sort();
+
+        let mut new_indices = vec![0; tasks_with_sources.len() + 1];
+        let mut order = vec![0];
+        for (i, (task, source, idx)) in tasks_with_sources.into_iter().enumerate() {
+            self.tasks.push(task);
+            self.task_sources.push(source);
+            new_indices[idx] = i + 1;
+            order.push(idx);
+        }
+        if let Some(entry) = self.undo_stack.back_mut() {
+            entry.order = Some(order);
+            entry.marked = Some(self.marked.clone());
+        }
+        self.remap_task_indices(|idx| Some(new_indices[idx]));
+        self.update_counts();
+        self.filter_tasks();
+    }
+
+    pub fn toggle_view_completed(&mut self) {
+        self.omit_completed = !self.omit_completed;
+        self.filter_base = None;
+        self.filter_tasks();
+    }
+
+    /// Show only tasks which still need a context or project, or show all tasks
+    pub fn toggle_inbox(&mut self) {
+        self.inbox_only = !self.inbox_only;
+        self.filter_base = None;
+        self.filter_tasks();
+    }
+
+    pub fn inbox_only(&self) -> bool {
+        self.inbox_only
+    }
+
+    pub fn toggle_task_complete(&mut self) {
+        let Some(idx) = self.get_selected_task_idx() else {
+            return;
+        };
+        self.push_undo();
+        let completed = !self.tasks[idx].completed;
+        let today = self.today;
+        set_completed(self.task_mut(idx), completed, today);
+        self.modified_task = Some(idx);
+        if completed && self.complete_subtasks {
+            for child in todo_txt::tasks::descendants_of(&self.tasks, idx) {
+                if !self.tasks[child].completed {
+                    set_completed(self.task_mut(child), true, today);
+                    self.modified_task = None;
+                }
+            }
+        }
+        if self.hide_blocked {
+            // Show tasks unblocked by the completion
+            self.update_state_after_edit();
+        } else {
+            self.update_counts();
+        }
+    }
+
+    /// Apply a modification to the selected task, then update lists and counts
+    fn modify_selected_task<F: FnOnce(&mut todo_txt::task::Task)>(&mut self, modify: F) {
+        let Some(idx) = self.get_selected_task_idx() else {
+            return;
+        };
+        self.push_undo();
+        modify(self.task_mut(idx));
+        self.modified_task = Some(idx);
+        self.update_state_after_edit();
+    }
+
+    /// Remove the tasks at the given indices into `tasks` to the trash, keeping
+    /// sources and marks of the remaining tasks in step
+    fn remove_tasks(&mut self, indices: &HashSet<usize>) {
+        let mut removed: Vec<usize> = indices.iter().copied().collect();
+        removed.sort_unstable();
+        if let Some(entry) = self.undo_stack.back_mut() {
+            entry.marked = Some(self.marked.clone());
+        }
+        for idx in removed {
+            let task = self.tasks[idx].clone();
+            let source = self.task_sources[idx];
+            self.trash.push((task.clone(), source));
+            self.trash_unsaved.push(task.clone());
+            if let Some(entry) = self.undo_stack.back_mut() {
+                entry.removed.push((idx, task, source));
+            }
+        }
+
+        let mut idx = 0;
+        self.tasks.retain(|_| {
+            idx += 1;
+            !indices.contains(&(idx - 1))
+        });
+        let mut idx = 0;
+        self.task_sources.retain(|_| {
+            idx += 1;
+            !indices.contains(&(idx - 1))
+        });
+        self.remap_task_indices(|idx| {
+            let shift = indices.iter().filter(|removed| **removed < idx).count();
+            (!indices.contains(&idx)).then_some(idx - shift)
+        });
+    }
+
+    /// Move marks and timers along with their tasks, given the new index into
+    /// `tasks` of each task, or None if it was removed
+    fn remap_task_indices<F: Fn(usize) -> Option<usize>>(&mut self, remap: F) {
+        self.marked = self.marked.iter().filter_map(|idx| remap(*idx)).collect();
+        self.timer = self
+            .timer
+            .and_then(|(idx, start)| Some((remap(idx)?, start)));
+        self.pomodoro = self.pomodoro.take().and_then(|mut pomodoro| {
+            pomodoro.task_idx = remap(pomodoro.task_idx)?;
+            Some(pomodoro)
+        });
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Marks and bulk commands
+    //
+    //--------------------------------------------------------------------------
+
+    pub fn is_marked(&self, task_idx: usize) -> bool {
+        self.marked.contains(&task_idx)
+    }
+
+    /// Number of marked tasks, including those currently filtered out
+    pub fn marked_count(&self) -> usize {
+        self.marked.len()
+    }
+
+    /// Mark or unmark the selected task, then move the selection down
+    pub fn toggle_mark(&mut self) {
+        if let Some(idx) = self.get_selected_task_idx() {
+            if !self.marked.remove(&idx) {
+                self.marked.insert(idx);
+            }
+            self.task_list.next();
+        }
+    }
+
+    /// Mark all tasks in the task list, or unmark them if they are all marked
+    /// already
+    pub fn mark_all(&mut self) {
+        let visible: Vec<usize> = self.task_list.items()[1..].to_vec();
+        if visible.iter().all(|idx| self.marked.contains(idx)) {
+            for idx in visible {
+                self.marked.remove(&idx);
+            }
+        } else {
+            self.marked.extend(visible);
+        }
+    }
+
+    /// Invert the marks of all tasks in the task list
+    pub fn invert_marks(&mut self) {
+        for idx in self.task_list.items()[1..].iter() {
+            if !self.marked.remove(idx) {
+                self.marked.insert(*idx);
+            }
+        }
+    }
+
+    /// Enter bulk mode to choose a command to apply to all marked tasks
+    pub fn enter_bulk_mode(&mut self) {
+        if self.marked.is_empty() {
+            self.error_msg = "No marked tasks".to_string();
+            return;
+        }
+        self.bulk_list = SelectionList::with_items(BulkCommand::ALL.to_vec());
+        self.mode = Mode::Bulk(None);
+    }
+
+    pub fn bulk_list(&self) -> &SelectionList<BulkCommand> {
+        &self.bulk_list
+    }
+
+    /// Choose the command selected in the bulk menu.  Commands which need an
+    /// argument list the possible choices in the pick list, others are
+    /// confirmed directly.
+    pub fn select_bulk_command(&mut self) {
+        let Some(command) = App::get_selected_item_or_first(&self.bulk_list) else {
+            return;
+        };
+        let choices = match command {
+            BulkCommand::Complete => return self.confirm_bulk_action(BulkAction::Complete),
+            BulkCommand::Delete => return self.confirm_bulk_action(BulkAction::Delete),
+            BulkCommand::SetPriority => std::iter::once("none".to_string())
+                .chain(('A'..='Z').map(String::from))
+                .collect(),
+            BulkCommand::AddContext => self.context_tree.names().to_vec(),
+            BulkCommand::AddProject => self.project_tree.names().to_vec(),
+            BulkCommand::RemoveContext => {
+                let marked: Vec<&todo_txt::task::Task> =
+                    self.marked.iter().map(|idx| &self.tasks[*idx]).collect();
+                todo_txt::tasks::collect_contexts(&marked, true)
+            }
+            BulkCommand::RemoveProject => {
+                let marked: Vec<&todo_txt::task::Task> =
+                    self.marked.iter().map(|idx| &self.tasks[*idx]).collect();
+                todo_txt::tasks::collect_projects(&marked, true)
+            }
+            BulkCommand::MoveToFile => {
+                if self.sources.len() < 2 {
+                    self.error_msg = "Only one file is open".to_string();
+                    self.mode = Mode::Normal;
+                    return;
+                }
+                self.sources
+                    .iter()
+                    .map(|source| source.to_string())
+                    .collect()
+            }
+        };
+        if choices.is_empty() {
+            self.error_msg = format!("Nothing to {}", command.name());
+            self.mode = Mode::Normal;
+            return;
+        }
+        self.pick_list = SelectionList::with_items(choices);
+        self.mode = Mode::Bulk(Some(command));
+    }
+
+    /// Choose the argument selected in the pick list for the pending bulk
+    /// command, then ask for confirmation
+    pub fn select_bulk_choice(&mut self) {
+        let (Mode::Bulk(Some(command)), Some(choice), Some(choice_idx)) = (
+            self.mode,
+            App::get_selected_item_or_first(&self.pick_list),
+            self.pick_list.selection(),
+        ) else {
+            return;
+        };
+        let action = match command {
+            BulkCommand::SetPriority if choice == "none" => BulkAction::SetPriority(None),
+            BulkCommand::SetPriority => BulkAction::SetPriority(choice.chars().next()),
+            BulkCommand::AddContext => BulkAction::AddContext(choice),
+            BulkCommand::RemoveContext => BulkAction::RemoveContext(choice),
+            BulkCommand::AddProject => BulkAction::AddProject(choice),
+            BulkCommand::RemoveProject => BulkAction::RemoveProject(choice),
+            BulkCommand::MoveToFile => BulkAction::MoveToFile(choice_idx),
+            BulkCommand::Complete | BulkCommand::Delete => return,
+        };
+        self.confirm_bulk_action(action);
+    }
+
+    fn confirm_bulk_action(&mut self, action: BulkAction) {
+        self.bulk_action = Some(action);
+        self.mode = Mode::Confirm(ConfirmedAction::Bulk);
+    }
+
+    /// Confirmation prompt for the pending bulk action
+    pub fn bulk_prompt(&self) -> String {
+        let action = match &self.bulk_action {
+            Some(BulkAction::Complete) => "Complete".to_string(),
+            Some(BulkAction::Delete) => "Delete".to_string(),
+            Some(BulkAction::SetPriority(Some(p))) => format!("Set priority ({}) on", p),
+            Some(BulkAction::SetPriority(None)) => "Clear priority on".to_string(),
+            Some(BulkAction::AddContext(context)) => format!("Add @{} to", context),
+            Some(BulkAction::RemoveContext(context)) => format!("Remove @{} from", context),
+            Some(BulkAction::AddProject(project)) => format!("Add +{} to", project),
+            Some(BulkAction::RemoveProject(project)) => format!("Remove +{} from", project),
+            Some(BulkAction::MoveToFile(source)) => {
+                format!("Move to {}", self.source_name(*source))
+            }
+            None => return String::new(),
+        };
+        match self.marked.len() {
+            1 => format!("{} 1 marked task", action),
+            n => format!("{} {} marked tasks", action, n),
+        }
+    }
+
+    /// Apply the pending bulk action to all marked tasks as a single undoable
+    /// modification.  Marks are kept so further commands can be applied.
+    fn apply_bulk_action(&mut self) {
+        let Some(action) = self.bulk_action.take() else {
+            return;
+        };
+        self.push_undo();
+        match action {
+            BulkAction::Delete => {
+                let marked = self.marked.clone();
+                self.remove_tasks(&marked);
+            }
+            BulkAction::MoveToFile(source) => {
+                for idx in self.marked.clone() {
+                    self.record_change(idx);
+                    self.task_sources[idx] = source;
+                }
+            }
+            _ => {
+                let today = self.today;
+                for idx in self.marked.clone() {
+                    let task = self.task_mut(idx);
+                    match &action {
+                        BulkAction::Complete if !task.completed => set_completed(task, true, today),
+                        BulkAction::SetPriority(priority) => task.priority = *priority,
+                        BulkAction::AddContext(context) => task.add_context(context),
+                        BulkAction::RemoveContext(context) => task.remove_context(context),
+                        BulkAction::AddProject(project) => task.add_project(project),
+                        BulkAction::RemoveProject(project) => task.remove_project(project),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        self.update_state_after_edit();
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Dependencies
+    //
+    //--------------------------------------------------------------------------
+
+    /// Returns true if the task at `task_idx` depends on an open task
+    pub fn is_blocked(&self, task_idx: usize) -> bool {
+        self.blocked.contains(&task_idx)
+    }
+
+    /// Hide or show tasks blocked by open dependencies
+    pub fn toggle_hide_blocked(&mut self) {
+        self.hide_blocked = !self.hide_blocked;
+        self.filter_base = None;
+        self.update_state_after_edit();
+    }
+
+    pub fn hide_blocked(&self) -> bool {
+        self.hide_blocked
+    }
+
+    /// List all other tasks so dependencies of the selected task can be added
+    /// or removed
+    pub fn enter_link_mode(&mut self) {
+        let Some(link_task) = self.get_selected_task_idx() else {
+            return;
+        };
+        self.link_task = link_task;
+        self.link_candidates = (1..self.tasks.len())
+            .filter(|idx| *idx != link_task)
+            .collect();
+        if self.link_candidates.is_empty() {
+            self.error_msg = "No other tasks to depend on".to_string();
+            return;
+        }
+        self.pick_list = SelectionList::with_items(
+            self.link_candidates
+                .iter()
+                .map(|idx| self.tasks[*idx].to_string())
+                .collect(),
+        );
+        self.mode = Mode::Link;
+    }
+
+    /// Returns true if the task being linked depends on the task at position
+    /// `pos` of the pick list
+    pub fn is_linked(&self, pos: usize) -> bool {
+        let candidate = &self.tasks[self.link_candidates[pos]];
+        candidate
+            .id()
+            .is_some_and(|id| self.tasks[self.link_task].dependencies().contains(&id))
+    }
+
+    /// Add or remove a dependency on the task selected in the pick list.  The
+    /// dependency is given an id first if it has none.
+    pub fn toggle_link(&mut self) {
+        let Some(pos) = self.pick_list.selection() else {
+            return;
+        };
+        let linked = self.is_linked(pos);
+        let candidate = self.link_candidates[pos];
+        self.push_undo();
+
+        let id = match self.tasks[candidate].id() {
+            Some(id) => id.to_string(),
+            None => {
+                let id = todo_txt::tasks::next_id(&self.tasks);
+                self.task_mut(candidate).set_tag("id", Some(&id));
+                id
+            }
+        };
+        let link_task = self.task_mut(self.link_task);
+        if linked {
+            link_task.remove_dependency(&id);
+        } else {
+            link_task.add_dependency(&id);
+        }
+
+        // Keep the pick list in step with the changed tasks
+        let selection = self.pick_list.selection();
+        self.pick_list = SelectionList::with_items(
+            self.link_candidates
+                .iter()
+                .map(|idx| self.tasks[*idx].to_string())
+                .collect(),
+        );
+        self.pick_list.select(selection);
+        self.update_state_after_edit();
+    }
+
+    pub fn exit_link_mode(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    /// Show the dependency tree of the selected task
+    pub fn enter_dependencies_mode(&mut self) {
+        if let Some(link_task) = self.get_selected_task_idx() {
+            self.link_task = link_task;
+            self.mode = Mode::Dependencies;
+        }
+    }
+
+    pub fn exit_dependencies_mode(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    /// Task shown in dependencies mode
+    pub fn dependencies_task(&self) -> &todo_txt::task::Task {
+        &self.tasks[self.link_task]
+    }
+
+    /// Depth and index into `tasks` of all tasks the task shown in dependencies
+    /// mode depends on, see [todo_txt::tasks::dependency_tree]
+    pub fn dependency_tree(&self) -> Vec<(usize, usize)> {
+        todo_txt::tasks::dependency_tree(&self.tasks, self.link_task)
+    }
+
+    /// Indices into `tasks` of the tasks directly depending on the task shown in
+    /// dependencies mode
+    pub fn dependents(&self) -> Vec<usize> {
+        todo_txt::tasks::dependents_of(&self.tasks, &self.tasks[self.link_task])
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Subtasks
+    //
+    //--------------------------------------------------------------------------
+
+    /// Complete open subtasks when completing their parent
+    pub fn set_complete_subtasks(&mut self, complete_subtasks: bool) {
+        self.complete_subtasks = complete_subtasks;
+    }
+
+    /// Show the task list as an outline of subtasks beneath their parents, or
+    /// as a flat list
+    pub fn toggle_outline(&mut self) {
+        let selected_task = self.get_selected_task_idx();
+        self.outline = !self.outline;
+        self.update_state_after_edit();
+
+        // Keep the selected task selected at its new position
+        if let Some(pos) =
+            selected_task.and_then(|idx| self.task_list.items().iter().position(|i| *i == idx))
+        {
+            self.task_list.select(Some(pos));
+        }
+    }
+
+    pub fn outline(&self) -> bool {
+        self.outline
+    }
+
+    /// Depth in the outline of the task at position `pos` of the task list
+    pub fn task_depth(&self, pos: usize) -> usize {
+        self.task_depths.get(pos).copied().unwrap_or(0)
+    }
+
+    pub fn has_subtasks(&self, task_idx: usize) -> bool {
+        self.tasks[task_idx]
+            .id()
+            .is_some_and(|id| self.parent_ids.contains(id))
+    }
+
+    pub fn is_collapsed_task(&self, task_idx: usize) -> bool {
+        self.tasks[task_idx]
+            .id()
+            .is_some_and(|id| self.collapsed_tasks.contains(id))
+    }
+
+    /// List all other tasks except subtasks so the parent of the selected
+    /// task can be chosen
+    pub fn enter_parent_mode(&mut self) {
+        let Some(link_task) = self.get_selected_task_idx() else {
+            return;
+        };
+        let descendants = todo_txt::tasks::descendants_of(&self.tasks, link_task);
+        self.link_task = link_task;
+        self.link_candidates = (1..self.tasks.len())
+            .filter(|idx| *idx != link_task && !descendants.contains(idx))
+            .collect();
+        if self.link_candidates.is_empty() {
+            self.error_msg = "No other tasks to be parent".to_string();
+            return;
+        }
+        self.pick_list = SelectionList::with_items(
+            self.link_candidates
+                .iter()
+                .map(|idx| self.tasks[*idx].to_string())
+                .collect(),
+        );
+        if let Some(pos) = (0..self.link_candidates.len()).find(|pos| self.is_parent(*pos)) {
+            self.pick_list.select(Some(pos));
+        }
+        self.mode = Mode::Parent;
+    }
+
+    /// Returns true if the task at position `pos` of the pick list is the
+    /// parent of the task being moved
+    pub fn is_parent(&self, pos: usize) -> bool {
+        let candidate = &self.tasks[self.link_candidates[pos]];
+        candidate
+            .id()
+            .is_some_and(|id| self.tasks[self.link_task].parent() == Some(id))
+    }
+
+    /// Make the task selected in the pick list the parent of the task being
+    /// moved, giving it an id first if it has none.  Choosing the current
+    /// parent makes the task a top level task again.
+    pub fn choose_parent(&mut self) {
+        let Some(pos) = self.pick_list.selection() else {
+            return;
+        };
+        let is_parent = self.is_parent(pos);
+        let candidate = self.link_candidates[pos];
+        self.push_undo();
+
+        if is_parent {
+            self.task_mut(self.link_task).set_tag("parent", None);
+        } else {
+            let id = match self.tasks[candidate].id() {
+                Some(id) => id.to_string(),
+                None => {
+                    let id = todo_txt::tasks::next_id(&self.tasks);
+                    self.task_mut(candidate).set_tag("id", Some(&id));
+                    id
+                }
+            };
+            self.task_mut(self.link_task).set_tag("parent", Some(&id));
+        }
+        self.mode = Mode::Normal;
+        self.update_state_after_edit();
+    }
+
+    pub fn exit_parent_mode(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Time tracking
+    //
+    //--------------------------------------------------------------------------
+
+    /// Append timed sessions to the given file when saving
+    pub fn set_time_log(&mut self, time_log: &'a str) {
+        self.time_log = Some(time_log);
+    }
+
+    /// Start timing the selected task, stopping any running timer first.  If
+    /// the selected task is already being timed, just stop its timer.
+    pub fn toggle_timer(&mut self) {
+        let Some(idx) = self.get_selected_task_idx() else {
+            return;
+        };
+        let timed = self.timer.is_some_and(|(timed, _)| timed == idx);
+        self.stop_timer();
+        if !timed {
+            self.timer = Some((idx, chrono::Local::now().naive_local()));
+        }
+    }
+
+    /// Stop the running timer, adding the time elapsed to the `spent:` tag of
+    /// the timed task and recording the session
+    pub fn stop_timer(&mut self) {
+        let Some((idx, start)) = self.timer else {
+            return;
+        };
+        let end = chrono::Local::now().naive_local();
+        self.push_undo();
+        self.timer = None;
+        self.task_mut(idx).add_spent(end - start);
+        self.modified_task = Some(idx);
+        self.sessions.push(Session {
+            start,
+            end,
+            task: self.tasks[idx].clone(),
+        });
+        self.update_state_after_edit();
+    }
+
+    /// Task being timed and the time elapsed since timing started
+    pub fn timer(&self) -> Option<(&todo_txt::task::Task, chrono::Duration)> {
+        self.timer.map(|(idx, start)| {
+            let elapsed = chrono::Local::now().naive_local() - start;
+            (&self.tasks[idx], elapsed)
+        })
+    }
+
+    /// Sessions timed this run, oldest first
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// Show time spent per project and context this week, from the sessions in
+    /// the time log and those not yet saved to it
+    pub fn enter_time_report_mode(&mut self) {
+        self.logged_sessions = self
+            .time_log
+            .and_then(|time_log| std::fs::File::open(time_log).ok())
+            .map(|file| {
+                use std::io::BufRead;
+                crate::timelog::read_sessions(&mut std::io::BufReader::new(file).lines())
+            })
+            .unwrap_or_default();
+        let unsaved = if self.time_log.is_some() {
+            &self.sessions[self.sessions_saved..]
+        } else {
+            &self.sessions[..]
+        };
+        self.logged_sessions.extend_from_slice(unsaved);
+
+        self.time_span = TimeSpan::Week;
+        self.time_report_date = self.today;
+        self.update_time_report();
+        self.mode = Mode::TimeReport;
+    }
+
+    /// Report over the day, week or month containing the reported date
+    pub fn set_time_span(&mut self, time_span: TimeSpan) {
+        self.time_span = time_span;
+        self.update_time_report();
+    }
+
+    /// Report over the previous (`step` < 0) or following period
+    pub fn step_time_report(&mut self, step: i32) {
+        self.time_report_date = self.time_span.step(self.time_report_date, step);
+        self.update_time_report();
+    }
+
+    fn update_time_report(&mut self) {
+        let (from, to) = self.time_span.range(self.time_report_date, self.week_start);
+        self.time_report = Some(TimeReport::new(&self.logged_sessions, from, to));
+    }
+
+    pub fn exit_time_report_mode(&mut self) {
+        self.logged_sessions.clear();
+        self.time_report = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// Time spent displayed in time report mode
+    pub fn time_report(&self) -> Option<&TimeReport> {
+        self.time_report.as_ref()
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Pomodoro
+    //
+    //--------------------------------------------------------------------------
+
+    pub fn set_pomodoro_lengths(
+        &mut self,
+        work_length: chrono::Duration,
+        break_length: chrono::Duration,
+    ) {
+        self.pomodoro_lengths = (work_length, break_length);
+    }
+
+    /// Start a pomodoro on the selected task, replacing any running pomodoro.
+    /// If the selected task already has the pomodoro, just stop it.
+    pub fn toggle_pomodoro(&mut self) {
+        let Some(idx) = self.get_selected_task_idx() else {
+            return;
+        };
+        self.record_pomodoros();
+        let running = self
+            .pomodoro
+            .take()
+            .is_some_and(|pomodoro| pomodoro.task_idx == idx);
+        if !running {
+            let (work_length, break_length) = self.pomodoro_lengths;
+            let now = chrono::Local::now().naive_local();
+            self.pomodoro = Some(Pomodoro::new(idx, now, work_length, break_length));
+        }
+    }
+
+    /// Move the pomodoro on to the phase under way, returning the last phase
+    /// which ended.  Completed work periods are counted by the
+    /// [crate::pomodoro::POMODORO_TAG_KEY] tag of the task.
+    pub fn tick_pomodoro(&mut self) -> Option<Phase> {
+        let now = chrono::Local::now().naive_local();
+        let ended = self
+            .pomodoro
+            .as_mut()
+            .and_then(|pomodoro| pomodoro.tick(now));
+        if let Some((_, work_periods)) = ended {
+            self.pomodoros_unrecorded += work_periods;
+        }
+        // Work periods which ended while editing are counted once done
+        self.record_pomodoros();
+        ended.map(|(phase, _)| phase)
+    }
+
+    /// Count the ended work periods on the pomodoro's task, unless the user is
+    /// editing, when the edited text would overwrite the count.  This happens
+    /// in the background, so it is not an undoable modification.  Instead the
+    /// count is also made in the undo history, so undoing the user's own
+    /// modifications keeps it.
+    fn record_pomodoros(&mut self) {
+        if self.pomodoros_unrecorded == 0 || matches!(self.mode, Mode::Edit | Mode::Detail) {
+            return;
+        }
+        let Some(pomodoro) = &self.pomodoro else {
+            self.pomodoros_unrecorded = 0;
+            return;
+        };
+        let added = std::mem::take(&mut self.pomodoros_unrecorded);
+        let start = pomodoro.start;
+        add_pomodoros(&mut self.tasks[pomodoro.task_idx], added);
+        self.modified_task = Some(pomodoro.task_idx);
+        for entry in &mut self.undo_stack {
+            let Some(idx) = entry
+                .pomodoro
+                .as_ref()
+                .filter(|p| p.start == start)
+                .map(|p| p.task_idx)
+            else {
+                continue;
+            };
+            // Tasks the entry does not restore keep the count as it is
+            if let Some((task, _)) = entry.changed.get_mut(&idx) {
+                add_pomodoros(task, added);
+            }
+            if let Some((_, task, _)) = entry.removed.iter_mut().find(|(i, ..)| *i == idx) {
+                add_pomodoros(task, added);
+            }
+        }
+        self.update_state_after_edit();
+    }
+
+    /// Task of the running pomodoro, its current phase and the time left in it
+    pub fn pomodoro(&self) -> Option<(&todo_txt::task::Task, Phase, chrono::Duration)> {
+        let pomodoro = self.pomodoro.as_ref()?;
+        let remaining = pomodoro.remaining(chrono::Local::now().naive_local());
+        Some((&self.tasks[pomodoro.task_idx], pomodoro.phase, remaining))
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Events
+    //
+    //--------------------------------------------------------------------------
+
+    /// Update the app for an event from outside of the user's input, returning
+    /// true if the user should be alerted, eg, by ringing the terminal bell
+    pub fn handle_event(&mut self, event: AppEvent) -> bool {
+        match event {
+            AppEvent::Tick => {
+                let today = chrono::Local::now().date_naive();
+                if today != self.today {
+                    // Due and threshold dates are relative to today
+                    self.today = today;
+                    self.update_state_after_edit();
+                }
+                self.tick_pomodoro().is_some()
+            }
+            AppEvent::SourceModified(source, modified) => {
+                if source >= self.sources.len()
+                    || self.saved_at.is_some_and(|saved_at| modified <= saved_at)
+                {
+                    return false;
+                }
+                if !self.modified_sources.contains(&source) {
+                    self.modified_sources.push(source);
+                    self.modified_sources.sort_unstable();
+                }
+                self.error_msg = format!(
+                    "'{}' was modified by another program",
+                    self.source_name(source)
+                );
+                true
+            }
+        }
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Trash
+    //
+    //--------------------------------------------------------------------------
+
+    /// Append deleted tasks to the given file when saving
+    pub fn set_trash_file(&mut self, trash_file: &'a str) {
+        self.trash_file = Some(trash_file);
+    }
+
+    /// Tasks deleted this session, with the index of their source, oldest first
+    pub fn trash(&self) -> &[(todo_txt::task::Task, usize)] {
+        &self.trash
+    }
+
+    /// List deleted tasks, most recently deleted first, so they can be restored
+    pub fn enter_trash_mode(&mut self) {
+        if self.trash.is_empty() {
+            self.error_msg = "Trash is empty".to_string();
+            return;
+        }
+        self.pick_list = SelectionList::with_items(
+            self.trash
+                .iter()
+                .rev()
+                .map(|(task, _)| task.to_string())
+                .collect(),
+        );
+        self.mode = Mode::Trash;
+    }
+
+    /// Move the task selected in the trash list back to its source file
+    pub fn restore_from_trash(&mut self) {
+        let Some(selection) = self.pick_list.selection() else {
+            return;
+        };
+        self.push_undo();
+        let pos = self.trash.len() - 1 - selection;
+        let (task, source) = self.trash.remove(pos);
+        if let Some(entry) = self.undo_stack.back_mut() {
+            entry.restored = Some((pos, (task.clone(), source)));
+        }
+        self.tasks.push(task);
+        self.task_sources.push(source);
+        self.update_state_after_edit();
+
+        if self.trash.is_empty() {
+            self.mode = Mode::Normal;
+        } else {
+            self.enter_trash_mode();
+            self.pick_list
+                .select(Some(selection.min(self.trash.len() - 1)));
+        }
+    }
+
+    pub fn exit_trash_mode(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Undo
+    //
+    //--------------------------------------------------------------------------
+
+    /// Start recording the following modification so it can be undone
+    fn push_undo(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(UndoEntry {
+            len: self.tasks.len(),
+            changed: HashMap::new(),
+            removed: Vec::new(),
+            order: None,
+            marked: None,
+            trash_len: self.trash.len(),
+            restored: None,
+            timer: self.timer,
+            pomodoro: self.pomodoro.clone(),
+            sessions: self.sessions.len(),
+        });
+    }
+
+    /// Record the task at `idx` and its source before they are changed
+    fn record_change(&mut self, idx: usize) {
+        if let Some(entry) = self.undo_stack.back_mut() {
+            if idx < entry.len {
+                entry
+                    .changed
+                    .entry(idx)
+                    .or_insert_with(|| (self.tasks[idx].clone(), self.task_sources[idx]));
+            }
+        }
+    }
+
+    /// The task at `idx`, to be changed as part of the recorded modification
+    fn task_mut(&mut self, idx: usize) -> &mut todo_txt::task::Task {
+        self.record_change(idx);
+        &mut self.tasks[idx]
+    }
+
+    /// Restore the task list from before the last modification
+    pub fn undo(&mut self) {
+        let Some(entry) = self.undo_stack.pop_back() else {
+            self.error_msg = "Nothing to undo".to_string();
+            return;
+        };
+
+        if let Some(order) = entry.order {
+            let mut tasks: Vec<_> = order
+                .into_iter()
+                .zip(self.tasks.drain(..).zip(self.task_sources.drain(..)))
+                .collect();
+            tasks.sort_unstable_by_key(|(idx, _)| *idx);
+            (self.tasks, self.task_sources) = tasks.into_iter().map(|(_, task)| task).unzip();
+        }
+
+        // Drop added tasks and put removed ones back in place
+        self.tasks.truncate(entry.len - entry.removed.len());
+        self.task_sources.truncate(entry.len - entry.removed.len());
+        if !entry.removed.is_empty() {
+            let mut removed = entry.removed.into_iter().peekable();
+            let mut kept = std::mem::take(&mut self.tasks)
+                .into_iter()
+                .zip(std::mem::take(&mut self.task_sources));
+            for idx in 0..entry.len {
+                let (task, source) = match removed.next_if(|(i, ..)| *i == idx) {
+                    Some((_, task, source)) => (task, source),
+                    None => kept.next().unwrap(),
+                };
+                self.tasks.push(task);
+                self.task_sources.push(source);
+            }
+        }
+        for (idx, (task, source)) in entry.changed {
+            self.tasks[idx] = task;
+            self.task_sources[idx] = source;
+        }
+
+        self.trash.truncate(entry.trash_len);
+        if let Some((pos, restored)) = entry.restored {
+            self.trash.insert(pos, restored);
+        }
+        if let Some(marked) = entry.marked {
+            self.marked = marked;
+        }
+        self.timer = entry.timer;
+        self.pomodoro = entry.pomodoro;
+        // Sessions already saved to the time log stay recorded
+        self.sessions
+            .truncate(entry.sessions.max(self.sessions_saved));
+        self.update_state_after_edit();
+    }
+
+    /// Set or clear the priority of the selected task
+    pub fn set_priority(&mut self, priority: Option<char>) {
+        self.mode = Mode::Normal;
+        match priority {
+            Some(p) if !p.is_ascii_alphabetic() => {
+                self.error_msg = format!("Invalid priority '{}'", p);
+            }
+            _ => self.modify_selected_task(|task| {
+                task.priority = priority.map(|p| p.to_ascii_uppercase());
+            }),
+        }
     }
 
-    pub fn mode(&self) -> Mode {
-        self.mode
+    /// Raise the priority of the selected task by one letter.  A task without a
+    /// priority is given the lowest priority currently in use (or A).
+    pub fn raise_priority(&mut self) {
+        let lowest = todo_txt::tasks::collect_priorities(&self.tasks, true)
+            .last()
+            .copied()
+            .unwrap_or('A');
+        self.modify_selected_task(|task| {
+            task.priority = match task.priority {
+                Some('A') => Some('A'),
+                Some(p) => char::from_u32(p as u32 - 1),
+                None => Some(lowest),
+            };
+        });
     }
 
-    pub fn focus(&self) -> Focus {
-        self.focus
-    }
-    
-    pub fn task_list(&self) -> &SelectionList<usize> {
-        &self.task_list
+    /// Lower the priority of the selected task by one letter, removing the
+    /// priority when lowered past Z
+    pub fn lower_priority(&mut self) {
+        self.modify_selected_task(|task| {
+            task.priority = match task.priority {
+                Some('Z') | None => None,
+                Some(p) => char::from_u32(p as u32 + 1),
+            };
+        });
     }
 
-    /// Create list of items for display, including ALL_TOKEN or NEW_TOKEN header
-    pub fn get_task_items(tasks: &[todo_txt::task::Task]) -> Vec<usize> {
-        // tasks already contains NEW_TOKEN task
-        Vec::from_iter(0..tasks.len())
+    pub fn enter_priority_mode(&mut self) {
+        if self.get_selected_task().is_some() {
+            self.mode = Mode::Priority;
+        }
     }
 
-    /// See [get_task_items]
-    pub fn get_context_items(tasks: &[todo_txt::task::Task]) -> Vec<String> {
-        [
-            vec![ALL_TOKEN.to_string()],
-            todo_txt::tasks::collect_contexts(tasks, true),
-        ]
-        .concat()
+    /// Enter the date picker, starting from the selected task's due date or today
+    pub fn enter_date_picker(&mut self) {
+        if let Some(task) = self.get_selected_task() {
+            self.picker_date = task.due_date().unwrap_or(self.today);
+            self.mode = Mode::DatePicker;
+        }
     }
 
-    /// See [get_task_items]
-    pub fn get_project_items(tasks: &[todo_txt::task::Task]) -> Vec<String> {
-        [
-            vec![ALL_TOKEN.to_string()],
-            todo_txt::tasks::collect_projects(tasks, true),
-        ]
-        .concat()
+    /// Date currently highlighted in the date picker
+    pub fn picker_date(&self) -> chrono::NaiveDate {
+        self.picker_date
     }
 
-    /// See [get_task_items]
-    pub fn get_priority_items(tasks: &[todo_txt::task::Task]) -> Vec<String> {
-        [
-            vec![ALL_TOKEN.to_string()],
-            todo_txt::tasks::collect_priorities(tasks, true)
-                .iter()
-                .map(|p| p.to_string())
-                .collect(),
-        ]
-        .concat()
+    /// Move the date picker selection by a number of days
+    pub fn move_picker_date(&mut self, days: i64) {
+        self.picker_date += chrono::Duration::days(days);
     }
 
-    fn get_selected_item<T: Clone>(list: &SelectionList<T>) -> Option<T> {
-        match list.selection() {
-            Some(i) if i > 0 => list.items().get(i).cloned(),
-            _ => None,
+    /// Move the date picker selection by a number of months, clamping the day
+    /// to the length of the new month
+    pub fn move_picker_month(&mut self, months: i32) {
+        let months = self.picker_date.year() * 12 + self.picker_date.month0() as i32 + months;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+        let mut day = self.picker_date.day();
+        loop {
+            if let Some(date) = chrono::NaiveDate::from_ymd_opt(year, month, day) {
+                self.picker_date = date;
+                return;
+            }
+            day -= 1;
         }
     }
 
-    pub fn save(&self) -> std::io::Result<()> {
-        let mut file = match std::fs::File::create(self.filepath) {
-            Err(err) => {
-                println!("Failed to open file '{}': {}", &self.filepath, err);
-                std::process::exit(0);
-            }
-            Ok(file) => file,
+    /// Exit the date picker, setting the selected task's due date to the picked
+    /// date, or removing it if `set` is false
+    pub fn exit_date_picker(&mut self, set: bool) {
+        self.mode = Mode::Normal;
+        let due = self.picker_date.to_string();
+        self.modify_selected_task(|task| task.set_tag("due", set.then_some(due.as_str())));
+    }
+
+    /// List existing contexts or projects so they can be added to or removed
+    /// from the selected task
+    pub fn enter_pick_mode(&mut self, kind: PickKind) {
+        if self.get_selected_task().is_none() {
+            return;
+        }
+        let names = match kind {
+            PickKind::Context => self.context_tree.names().to_vec(),
+            PickKind::Project => self.project_tree.names().to_vec(),
         };
-        for task in &self.tasks[1..] {
-            writeln!(&mut file, "{}", task)?;
+        if names.is_empty() {
+            self.error_msg = match kind {
+                PickKind::Context => "No contexts to choose from".to_string(),
+                PickKind::Project => "No projects to choose from".to_string(),
+            };
+            return;
         }
-        Ok(())
+        self.pick_list = SelectionList::with_items(names);
+        self.mode = Mode::Pick(kind);
     }
 
-    pub fn start_frame(&mut self) {}
-
-    pub fn end_frame(&mut self, frame_time: f64) {
-        self.frame_time = frame_time;
+    pub fn pick_list(&self) -> &SelectionList<String> {
+        &self.pick_list
     }
 
-    pub fn sort_tasks(&mut self) {
-        self.tasks.sort();
+    /// Returns true if the selected task has the given context or project
+    pub fn is_picked(&self, kind: PickKind, name: &str) -> bool {
+        match (kind, self.get_selected_task()) {
+            (PickKind::Context, Some(task)) => task.contexts().iter().any(|c| c == name),
+            (PickKind::Project, Some(task)) => task.projects().iter().any(|p| p == name),
+            (_, None) => false,
+        }
     }
 
-    pub fn toggle_view_completed(&mut self) {
-        self.omit_completed = !self.omit_completed;
-        self.filter_tasks();
+    /// Add the context or project selected in the pick list to the selected
+    /// task, or remove it if already present
+    pub fn toggle_picked(&mut self) {
+        let (Mode::Pick(kind), Some(name)) =
+            (self.mode, App::get_selected_item_or_first(&self.pick_list))
+        else {
+            return;
+        };
+        let picked = self.is_picked(kind, &name);
+        let selected_task_idx = self.get_selected_task_idx();
+        self.modify_selected_task(|task| match (kind, picked) {
+            (PickKind::Context, false) => task.add_context(&name),
+            (PickKind::Context, true) => task.remove_context(&name),
+            (PickKind::Project, false) => task.add_project(&name),
+            (PickKind::Project, true) => task.remove_project(&name),
+        });
+
+        // Keep picking for the same task, even if it is now filtered out of the
+        // task list, by dropping the filters
+        if self.get_selected_task_idx() != selected_task_idx {
+            self.context_list.select(Some(0));
+            self.project_list.select(Some(0));
+            self.priority_list.select(Some(0));
+            self.filter_tasks();
+            let position = self
+                .task_list
+                .items()
+                .iter()
+                .position(|idx| Some(*idx) == selected_task_idx);
+            self.task_list.select(position);
+        }
     }
 
-    pub fn toggle_task_complete(&mut self) {
-        if let Some(task) = self.get_selected_task_mut() {
-            match task.completed {
-                true => {
-                    task.completed = false;
-                    task.date_completed = None;
-                }
-                false => {
-                    if task.date_created.is_some() {
-                        task.date_completed = Some(chrono::Local::now().date_naive());
-                    }
-                    task.completed = true;
-                }
-            }
-        }
+    pub fn exit_pick_mode(&mut self) {
+        self.mode = Mode::Normal;
     }
 
     pub fn get_selected_task_list_idx(&self) -> Option<usize> {
@@ -258,39 +1882,69 @@ impl<'a> App<'a> {
     }
 
     pub fn filter_tasks(&mut self) {
-        let tasks_filter = todo_txt::tasks::TasksFilter::new(&self.tasks[1..])
-            .without_completed(self.omit_completed)
-            .with_project(self.get_selected_project().as_deref())
-            .with_context(self.get_selected_context().as_deref())
-            .with_priority(self.get_selected_priority());
-
-        let task_items = [
-            vec![0usize],
-            tasks_filter.task_indices.iter().map(|i| i + 1).collect(),
-        ]
-        .concat();
+        // Filters not tied to the side panes only change along with the tasks
+        // or view toggles, so are applied once and reused
+        let base = match self.filter_base.take() {
+            Some(base) => base,
+            None => crate::filter::FilterIndex::new(
+                self.base_filter((1..self.tasks.len()).collect())
+                    .into_iter()
+                    .map(|idx| (idx, &self.tasks[idx])),
+            ),
+        };
+        let mut indices = base.select(
+            self.get_selected_context().as_deref(),
+            self.get_selected_project().as_deref(),
+            self.get_selected_priority(),
+        );
+        self.filter_base = Some(base);
+
+        if self.outline {
+            indices = todo_txt::tasks::TasksFilter::with_indices(
+                &self.tasks[1..],
+                indices.iter().map(|i| i - 1).collect(),
+            )
+            .with_ancestors(true)
+            .task_indices
+            .iter()
+            .map(|i| i + 1)
+            .collect();
+        }
+
+        let mut task_items = vec![0usize];
+        self.task_depths = vec![0];
+        if self.outline {
+            // Skip subtasks beneath a collapsed task
+            let mut collapsed_depth = None;
+            for (depth, idx) in todo_txt::tasks::outline(&self.tasks, &indices) {
+                if collapsed_depth.is_some_and(|collapsed| depth > collapsed) {
+                    continue;
+                }
+                collapsed_depth = self.is_collapsed_task(idx).then_some(depth);
+                task_items.push(idx);
+                self.task_depths.push(depth);
+            }
+        } else {
+            task_items.extend(indices);
+        }
 
         self.task_list = SelectionList::with_items(task_items);
     }
 
     pub fn update_state_after_edit(&mut self) {
+        self.update_counts();
+
         // a) Cache selected context from previous frame
         // b) regenerate list of contexts after task list edit
         // c) if selected context is still present, reselect it
-        let selected_context = self.get_selected_context();
-        self.context_list = SelectionList::with_items(App::get_context_items(&self.tasks));
-        if let Some(context) = selected_context {
-            self.context_list
-                .select(self.context_list.items().iter().position(|x| x == &context));
-        }
+        self.context_tree
+            .set_names(&todo_txt::tasks::collect_contexts(&self.tasks, true));
+        self.refresh_context_list();
 
         // repeat for projects
-        let selected_project = self.get_selected_project();
-        self.project_list = SelectionList::with_items(App::get_project_items(&self.tasks));
-        if let Some(project) = selected_project {
-            self.project_list
-                .select(self.project_list.items().iter().position(|x| x == &project));
-        }
+        self.project_tree
+            .set_names(&todo_txt::tasks::collect_projects(&self.tasks, true));
+        self.refresh_project_list();
 
         // repeat for priority
         let selected_priority = self.get_selected_priority();
@@ -310,26 +1964,98 @@ impl<'a> App<'a> {
         let selected_task_list_idx = self.task_list.selection();
 
         self.filter_tasks();
-        self.task_list.select(selected_task_list_idx);
+        // Keep a selection past the end of a shortened list on the last task
+        let last = self.task_list.items().len() - 1;
+        self.task_list
+            .select(selected_task_list_idx.map(|idx| idx.min(last)));
 
         // If current task is now filtered out, unselect
         if let Some(task) = selected_task {
-            if task.priority != self.get_selected_priority() {
-                self.task_list.select(Some(0));
+            if let Some(selected_priority) = self.get_selected_priority() {
+                if task.priority != Some(selected_priority) {
+                    self.task_list.select(Some(0));
+                }
             }
             if let Some(selected_context) = self.get_selected_context() {
-                if !task.contexts().contains(&selected_context) {
+                if !task
+                    .contexts()
+                    .iter()
+                    .any(|c| todo_txt::tasks::is_within(c, &selected_context))
+                {
                     self.task_list.select(Some(0));
                 }
             }
             if let Some(selected_project) = self.get_selected_project() {
-                if !task.projects().contains(&selected_project) {
+                if !task
+                    .projects()
+                    .iter()
+                    .any(|p| todo_txt::tasks::is_within(p, &selected_project))
+                {
                     self.task_list.select(Some(0));
                 }
             }
         }
     }
 
+    /// Regenerate the context list from the context tree, reselecting the
+    /// previously selected context if it is still present
+    fn refresh_context_list(&mut self) {
+        let selected_context = self.get_selected_context();
+        self.context_list = SelectionList::with_items(App::get_hierarchy_items(&self.context_tree));
+        if let Some(context) = selected_context {
+            self.context_list
+                .select(self.context_list.items().iter().position(|x| x == &context));
+        }
+    }
+
+    /// See [refresh_context_list]
+    fn refresh_project_list(&mut self) {
+        let selected_project = self.get_selected_project();
+        self.project_list = SelectionList::with_items(App::get_hierarchy_items(&self.project_tree));
+        if let Some(project) = selected_project {
+            self.project_list
+                .select(self.project_list.items().iter().position(|x| x == &project));
+        }
+    }
+
+    /// Collapse or expand the selected node of the focused context or project
+    /// tree
+    pub fn toggle_collapse(&mut self) {
+        if self.mode != Mode::Normal {
+            return;
+        }
+        match self.focus {
+            Focus::Contexts => {
+                if let Some(context) = self.get_selected_context() {
+                    self.context_tree.toggle(&context);
+                    self.refresh_context_list();
+                }
+            }
+            Focus::Projects => {
+                if let Some(project) = self.get_selected_project() {
+                    self.project_tree.toggle(&project);
+                    self.refresh_project_list();
+                }
+            }
+            Focus::Tasks => {
+                let Some(idx) = self.get_selected_task_idx() else {
+                    return;
+                };
+                if !self.outline || !self.has_subtasks(idx) {
+                    return;
+                }
+                let id = self.tasks[idx].id().unwrap_or_default().to_string();
+                if !self.collapsed_tasks.remove(&id) {
+                    self.collapsed_tasks.insert(id);
+                }
+                let selection = self.task_list.selection();
+                self.filter_tasks();
+                self.task_list.select(selection);
+            }
+            Focus::Priorities => {}
+        }
+    }
+
     pub fn navigate_up(&mut self) {
         match self.mode {
             Mode::Normal => match self.focus {
@@ -351,6 +2077,28 @@ impl<'a> App<'a> {
             },
             Mode::Edit => {}
             Mode::Help => {}
+            Mode::Report => {}
+            Mode::Detail => {
+                self.field_list.previous();
+            }
+            Mode::Priority => {}
+            Mode::DatePicker => {
+                self.move_picker_date(-7);
+            }
+            Mode::Pick(_) => {
+                self.pick_list.previous();
+            }
+            Mode::Trash | Mode::Link | Mode::Parent => {
+                self.pick_list.previous();
+            }
+            Mode::Dependencies => {}
+            Mode::TimeReport => {}
+            Mode::Bulk(None) => {
+                self.bulk_list.previous();
+            }
+            Mode::Bulk(Some(_)) => {
+                self.pick_list.previous();
+            }
             Mode::Confirm(_) => {}
         }
     }
@@ -376,6 +2124,28 @@ impl<'a> App<'a> {
             },
             Mode::Edit => {}
             Mode::Help => {}
+            Mode::Report => {}
+            Mode::Detail => {
+                self.field_list.next();
+            }
+            Mode::Priority => {}
+            Mode::DatePicker => {
+                self.move_picker_date(7);
+            }
+            Mode::Pick(_) => {
+                self.pick_list.next();
+            }
+            Mode::Trash | Mode::Link | Mode::Parent => {
+                self.pick_list.next();
+            }
+            Mode::Dependencies => {}
+            Mode::TimeReport => {}
+            Mode::Bulk(None) => {
+                self.bulk_list.next();
+            }
+            Mode::Bulk(Some(_)) => {
+                self.pick_list.next();
+            }
             Mode::Confirm(_) => {}
         }
     }
@@ -398,6 +2168,19 @@ impl<'a> App<'a> {
             },
             Mode::Edit => {}
             Mode::Help => {}
+            Mode::Report => {}
+            Mode::Detail => {}
+            Mode::Priority => {}
+            Mode::DatePicker => {
+                self.move_picker_date(1);
+            }
+            Mode::Pick(_) => {}
+            Mode::Trash => {}
+            Mode::Link => {}
+            Mode::Dependencies => {}
+            Mode::Parent => {}
+            Mode::TimeReport => {}
+            Mode::Bulk(_) => {}
             Mode::Confirm(_) => {}
         }
     }
@@ -420,6 +2203,19 @@ impl<'a> App<'a> {
             },
             Mode::Edit => {}
             Mode::Help => {}
+            Mode::Report => {}
+            Mode::Detail => {}
+            Mode::Priority => {}
+            Mode::DatePicker => {
+                self.move_picker_date(-1);
+            }
+            Mode::Pick(_) => {}
+            Mode::Trash => {}
+            Mode::Link => {}
+            Mode::Dependencies => {}
+            Mode::Parent => {}
+            Mode::TimeReport => {}
+            Mode::Bulk(_) => {}
             Mode::Confirm(_) => {}
         }
     }
@@ -428,28 +2224,53 @@ impl<'a> App<'a> {
         if let Mode::Confirm(action) = &self.mode {
             match action {
                 ConfirmedAction::Save => {
-                    self.save().expect("Failed to save file");
+                    if let Err(err) = self.save() {
+                        self.error_msg = format!("Failed to save: {}", err);
+                    }
                     self.mode = Mode::Normal;
                 }
                 ConfirmedAction::Sort => {
                     self.sort_tasks();
                     self.mode = Mode::Normal;
                 }
+                ConfirmedAction::Delete => {
+                    self.mode = Mode::Normal;
+                    if let Some(idx) = self.get_selected_task_idx() {
+                        self.push_undo();
+                        self.remove_tasks(&HashSet::from([idx]));
+                        self.update_state_after_edit();
+                    }
+                }
+                ConfirmedAction::Bulk => {
+                    self.apply_bulk_action();
+                    self.mode = Mode::Normal;
+                }
             }
         }
     }
 
     pub fn cancel_action(&mut self) {
+        self.bulk_action = None;
         self.mode = Mode::Normal;
     }
 
+    /// Whether the action awaiting confirmation goes ahead unless declined.
+    /// Saving over changes made by another program must be accepted explicitly.
+    pub fn confirm_by_default(&self) -> bool {
+        self.mode != Mode::Confirm(ConfirmedAction::Save) || self.modified_sources.is_empty()
+    }
+
     pub fn enter_confirm_mode(&mut self, action: ConfirmedAction) {
+        if action == ConfirmedAction::Delete && self.get_selected_task().is_none() {
+            return;
+        }
         self.mode = Mode::Confirm(action);
     }
 
     // enter editing mode and return the string of the currently selected task
     pub fn enter_edit_mode(&mut self) -> String {
         self.mode = Mode::Edit;
+        self.edit_target = EditTarget::Task;
 
         match self.get_selected_task() {
             Some(task) => task.to_string(),
@@ -457,31 +2278,112 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Enter editing mode on the field selected in the detail pane, returning
+    /// the field's current value.  Fields which are toggled rather than edited
+    /// are updated immediately and `None` is returned.
+    pub fn enter_field_edit_mode(&mut self) -> Option<String> {
+        let field = App::get_selected_item_or_first(&self.field_list)?;
+        if !field.is_editable() {
+            self.error_msg = format!("The {} field can not be edited", field.name());
+            return None;
+        }
+        if field.is_toggle() {
+            self.toggle_task_complete();
+            return None;
+        }
+
+        let value = field.value(self.get_selected_task()?);
+        self.mode = Mode::Edit;
+        self.edit_target = EditTarget::Field(field);
+        Some(value)
+    }
+
+    /// Enter editing mode to quickly add a new task, see [capture_task]
+    pub fn enter_capture_mode(&mut self) {
+        self.mode = Mode::Edit;
+        self.edit_target = EditTarget::Capture;
+    }
+
     pub fn exit_edit_mode(&mut self, input_str: Option<String>) {
-        self.mode = Mode::Normal;
-        if input_str.is_none() {
+        if self.edit_target == EditTarget::Capture {
+            self.edit_target = EditTarget::Task;
+            self.mode = Mode::Normal;
+            let Some(text) = input_str else {
+                return;
+            };
+            match crate::capture::capture_task(
+                &text,
+                &self.capture_default,
+                self.today,
+                self.week_start,
+            ) {
+                Some(task) => {
+                    self.push_undo();
+                    self.tasks.push(task);
+                    self.task_sources.push(0);
+                    self.update_state_after_edit();
+                }
+                None => self.error_msg = "Nothing to add".to_string(),
+            }
+            return;
+        }
+
+        if let EditTarget::Field(field) = self.edit_target {
+            self.edit_target = EditTarget::Task;
+            self.mode = Mode::Detail;
+            if let (Some(mut value), Some(mut edited)) =
+                (input_str, self.get_selected_task().cloned())
+            {
+                if field == TaskField::Due {
+                    if let Some(due) =
+                        todo_txt::date::parse_date(value.trim(), self.today, self.week_start)
+                    {
+                        value = due.to_string();
+                    }
+                }
+                match field.apply(&mut edited, &value) {
+                    Ok(()) => {
+                        self.push_undo();
+                        if let Some(idx) = self.get_selected_task_idx() {
+                            *self.task_mut(idx) = edited;
+                            self.modified_task = Some(idx);
+                        }
+                        self.update_state_after_edit();
+                        // The edit may have filtered the task out of the task list
+                        if self.get_selected_task().is_none() {
+                            self.mode = Mode::Normal;
+                        }
+                    }
+                    Err(err) => self.error_msg = err,
+                }
+            }
             return;
         }
 
-        let task_list_idx = self.task_list.selection().unwrap();
+        self.mode = Mode::Normal;
+        let (Some(task_list_idx), Some(task_str)) = (self.task_list.selection(), input_str) else {
+            return;
+        };
         let tasks_idx = self.task_list.items()[task_list_idx];
-        let task_str = input_str.unwrap();
 
+        // Emptying a task asks to delete it
         if task_str.is_empty() {
-            if task_list_idx != 0 {
-                self.tasks.remove(tasks_idx);
-                self.update_state_after_edit();
-            }
+            self.enter_confirm_mode(ConfirmedAction::Delete);
             return;
         }
 
+        let today = self.today;
+        let task_str = todo_txt::date::normalize_dates(&task_str, today, self.week_start);
         if let Ok(mut task) = todo_txt::task::Task::from_str(&task_str) {
+            self.push_undo();
             if task_list_idx == 0 {
-                task.date_created = Some(chrono::Local::now().date_naive());
+                task.date_created = Some(today);
                 self.tasks.push(task);
+                self.task_sources.push(0);
                 self.task_list.select(Some(0));
             } else {
-                self.tasks[tasks_idx] = task;
+                *self.task_mut(tasks_idx) = task;
+                self.modified_task = Some(tasks_idx);
             }
             self.update_state_after_edit();
         } else {
@@ -489,6 +2391,108 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Start editing tasks in an external editor, returning the text to edit
+    /// with one task per line.  Either the selected task or all tasks in the
+    /// task list are edited.  With [new] selected the text starts empty, so
+    /// tasks can be added.
+    pub fn enter_external_edit(&mut self, all_listed: bool) -> String {
+        self.external_edit = if all_listed {
+            self.task_list.items()[1..].to_vec()
+        } else {
+            self.get_selected_task_idx().into_iter().collect()
+        };
+        self.external_edit
+            .iter()
+            .map(|idx| format!("{}\n", self.tasks[*idx]))
+            .collect()
+    }
+
+    /// Apply the text returned from the external editor.  Changed lines
+    /// replace their tasks, removed lines delete them and added lines become new
+    /// tasks, as a single undoable modification.  Blank lines are ignored.
+    pub fn exit_external_edit(&mut self, text: std::io::Result<String>) {
+        let edited = std::mem::take(&mut self.external_edit);
+        let text = match text {
+            Ok(text) => text,
+            Err(err) => {
+                self.error_msg = format!("External edit failed: {}", err);
+                return;
+            }
+        };
+
+        let today = self.today;
+        let original: Vec<String> = edited
+            .iter()
+            .map(|idx| self.tasks[*idx].to_string())
+            .collect();
+        let lines: Vec<String> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| todo_txt::date::normalize_dates(line, today, self.week_start))
+            .collect();
+        let changes = crate::external::diff_lines(&original, &lines);
+        if changes.is_empty() {
+            return;
+        }
+
+        self.push_undo();
+        let mut deleted = HashSet::new();
+        for change in changes {
+            match change {
+                LineChange::Edit(i, line) => {
+                    if let Ok(task) = todo_txt::task::Task::from_str(&line) {
+                        *self.task_mut(edited[i]) = task;
+                    }
+                }
+                LineChange::Delete(i) => {
+                    deleted.insert(edited[i]);
+                }
+                LineChange::Add(line) => {
+                    if let Ok(mut task) = todo_txt::task::Task::from_str(&line) {
+                        if task.date_created.is_none() {
+                            task.date_created = Some(today);
+                        }
+                        self.tasks.push(task);
+                        self.task_sources.push(0);
+                    }
+                }
+            }
+        }
+        self.remove_tasks(&deleted);
+        self.update_state_after_edit();
+    }
+
+    /// What the text entered in edit mode will replace
+    pub fn edit_target(&self) -> EditTarget {
+        self.edit_target
+    }
+
+    /// Show or hide the detail pane for the selected task
+    pub fn toggle_detail(&mut self) {
+        self.show_detail = !self.show_detail;
+    }
+
+    pub fn show_detail(&self) -> bool {
+        self.show_detail
+    }
+
+    /// Enter detail mode to select and edit individual fields of the selected
+    /// task.  The detail pane is shown if hidden.
+    pub fn enter_detail_mode(&mut self) {
+        if self.get_selected_task().is_some() {
+            self.show_detail = true;
+            self.mode = Mode::Detail;
+        }
+    }
+
+    pub fn exit_detail_mode(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    pub fn field_list(&self) -> &SelectionList<TaskField> {
+        &self.field_list
+    }
+
     pub fn enter_help_mode(&mut self) {
         self.mode = Mode::Help;
     }
@@ -496,4 +2500,596 @@ impl<'a> App<'a> {
     pub fn exit_help_mode(&mut self) {
         self.mode = Mode::Normal;
     }
+
+    /// Enter report mode, computing statistics over all tasks
+    pub fn enter_report_mode(&mut self) {
+        self.report = Some(Report::new(&self.tasks[1..], self.today, self.week_start));
+        self.mode = Mode::Report;
+    }
+
+    pub fn exit_report_mode(&mut self) {
+        self.report = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// Statistics displayed in report mode
+    pub fn report(&self) -> Option<&Report> {
+        self.report.as_ref()
+    }
+}
+
+/// Add to the count of work periods of a task
+fn add_pomodoros(task: &mut todo_txt::task::Task, added: u32) {
+    let count = task
+        .tag(crate::pomodoro::POMODORO_TAG_KEY)
+        .and_then(|count| count.parse::<u32>().ok())
+        .unwrap_or(0);
+    task.set_tag(
+        crate::pomodoro::POMODORO_TAG_KEY,
+        Some(&(count + added).to_string()),
+    );
+}
+
+/// Complete or reopen a task.  The completion date is only recorded for tasks
+/// with a creation date, as required by the todo.txt format.
+fn set_completed(task: &mut todo_txt::task::Task, completed: bool, today: chrono::NaiveDate) {
+    if completed {
+        if task.date_created.is_some() {
+            task.date_completed = Some(today);
+        }
+    } else {
+        task.date_completed = None;
+    }
+    task.completed = completed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_names() {
+        let tasks = [todo_txt::task::Task::from_str("a").unwrap()];
+        let mut app = App::new("kdo", "work/todo.txt", &tasks);
+        assert_eq!(app.source_name(0), "todo.txt");
+        app.add_source("home/done.txt", &tasks);
+        app.add_source("/tmp/home/todo.txt", &tasks);
+        assert_eq!(app.filepath(), "work/todo.txt");
+        assert_eq!(app.source_name(0), "work/todo.txt");
+        assert_eq!(app.source_name(1), "done.txt");
+        assert_eq!(app.source_name(2), "home/todo.txt");
+    }
+
+    #[test]
+    fn bulk_actions() {
+        let tasks: Vec<todo_txt::task::Task> = ["a @home", "b +work", "c @home +work"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // Mark b, then invert to mark a and c
+        app.navigate_down();
+        app.navigate_down();
+        app.toggle_mark();
+        app.invert_marks();
+        assert_eq!(app.marked_count(), 2);
+        assert!(app.is_marked(1) && app.is_marked(3));
+
+        app.confirm_bulk_action(BulkAction::AddProject("garden".to_string()));
+        assert_eq!(app.bulk_prompt(), "Add +garden to 2 marked tasks");
+        app.confirm_action();
+        app.confirm_bulk_action(BulkAction::Delete);
+        app.confirm_action();
+        assert_eq!(app.tasks().len(), 2);
+        assert_eq!(app.marked_count(), 0);
+
+        // Both actions are undone in turn
+        app.undo();
+        assert_eq!(app.tasks()[3].to_string(), "c @home +work +garden");
+        assert_eq!(app.marked_count(), 2);
+        app.undo();
+        assert_eq!(app.tasks()[3].to_string(), "c @home +work");
+        app.undo();
+        assert_eq!(app.error_msg(), "Nothing to undo");
+
+        // Names only used by completed tasks can be removed too
+        let tasks: Vec<todo_txt::task::Task> = ["x a @done +old", "b @home"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+        app.invert_marks();
+        app.enter_bulk_mode();
+        for (command, names) in [
+            (BulkCommand::RemoveContext, ["done", "home"].as_slice()),
+            (BulkCommand::RemoveProject, ["old"].as_slice()),
+        ] {
+            let position = BulkCommand::ALL.iter().position(|c| *c == command);
+            app.bulk_list.select(position);
+            app.select_bulk_command();
+            assert_eq!(app.pick_list().items(), names);
+        }
+    }
+
+    #[test]
+    fn cached_filters() {
+        let tasks: Vec<todo_txt::task::Task> = ["x b @home", "a @work", "c @home"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // Side pane selections refine the tasks left by the view toggles
+        app.navigate_right();
+        app.navigate_down();
+        assert_eq!(app.task_list().items(), &[0, 1, 3]);
+        app.toggle_view_completed();
+        assert_eq!(app.task_list().items(), &[0, 3]);
+        app.navigate_down();
+        assert_eq!(app.task_list().items(), &[0, 2]);
+
+        // Display strings follow the tasks when sorting
+        app.toggle_view_completed();
+        app.sort_tasks();
+        assert_eq!(app.task_string(1), "a @work");
+        assert_eq!(app.task_list().items(), &[0, 1]);
+
+        // Edited tasks move between the lists of their old and new contexts,
+        // and leave the list when completed while completed tasks are hidden
+        let tasks: Vec<todo_txt::task::Task> = ["a @work", "b @home", "c @work"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+        app.navigate_right();
+        app.navigate_up();
+        assert_eq!(app.task_list().items(), &[0, 1, 3]);
+        app.navigate_left();
+        app.navigate_down();
+        app.enter_edit_mode();
+        app.exit_edit_mode(Some("a @home".to_string()));
+        assert_eq!(app.task_string(1), "a @home");
+        assert_eq!(app.task_list().items(), &[0, 3]);
+        app.navigate_right();
+        app.navigate_up();
+        assert_eq!(app.task_list().items(), &[0, 1, 2]);
+        app.toggle_view_completed();
+        app.navigate_left();
+        app.navigate_down();
+        app.enter_edit_mode();
+        app.exit_edit_mode(Some("x a @home".to_string()));
+        assert_eq!(app.task_list().items(), &[0, 2]);
+
+        // Undoing the edits restores the task to the lists it was in
+        app.undo();
+        assert_eq!(app.task_list().items(), &[0, 1, 2]);
+        app.undo();
+        assert_eq!(app.task_string(1), "a @work");
+        assert_eq!(app.task_list().items(), &[0, 2]);
+    }
+
+    #[test]
+    fn external_edit() {
+        let tasks: Vec<todo_txt::task::Task> = ["a", "b", "c"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        assert_eq!(app.enter_external_edit(true), "a\nb\nc\n");
+        app.exit_external_edit(Ok("a\n(A) b\n\n2023-01-02 d\n".to_string()));
+        let lines: Vec<String> = app.tasks()[1..].iter().map(|t| t.to_string()).collect();
+        assert_eq!(lines, ["a", "(A) b", "2023-01-02 d"]);
+
+        app.undo();
+        let lines: Vec<String> = app.tasks()[1..].iter().map(|t| t.to_string()).collect();
+        assert_eq!(lines, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn undo_history() {
+        let tasks: Vec<todo_txt::task::Task> = ["c", "a", "d", "b"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+        let lines =
+            |app: &App| -> Vec<String> { app.tasks()[1..].iter().map(|t| t.to_string()).collect() };
+
+        // Mark "a" and "b", sort, then delete the marked tasks
+        app.task_list.select(Some(2));
+        app.toggle_mark();
+        app.task_list.select(Some(4));
+        app.toggle_mark();
+        app.sort_tasks();
+        assert_eq!(lines(&app), ["a", "b", "c", "d"]);
+        assert!(app.is_marked(1) && app.is_marked(2));
+        app.confirm_bulk_action(BulkAction::Delete);
+        app.confirm_action();
+        assert_eq!(lines(&app), ["c", "d"]);
+
+        // Restore "a" from the trash and edit "d"
+        app.enter_trash_mode();
+        app.pick_list.select(Some(1));
+        app.restore_from_trash();
+        assert_eq!(app.trash().len(), 1);
+        app.task_list.select(Some(2));
+        app.enter_edit_mode();
+        app.exit_edit_mode(Some("(A) d".to_string()));
+        assert_eq!(lines(&app), ["c", "(A) d", "a"]);
+
+        // Each modification is undone in turn, with the tasks, marks and trash
+        app.undo();
+        assert_eq!(lines(&app), ["c", "d", "a"]);
+        app.undo();
+        assert_eq!(lines(&app), ["c", "d"]);
+        assert_eq!(app.trash().len(), 2);
+        app.undo();
+        assert_eq!(lines(&app), ["a", "b", "c", "d"]);
+        assert!(app.is_marked(1) && app.is_marked(2));
+        assert!(app.trash().is_empty());
+        app.undo();
+        assert_eq!(lines(&app), ["c", "a", "d", "b"]);
+        assert!(app.is_marked(2) && app.is_marked(4));
+    }
+
+    #[test]
+    fn trash() {
+        let tasks: Vec<todo_txt::task::Task> = ["a", "b"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // Emptying a task asks for confirmation before deleting it
+        app.navigate_down();
+        app.enter_edit_mode();
+        app.exit_edit_mode(Some(String::new()));
+        assert!(app.mode() == Mode::Confirm(ConfirmedAction::Delete));
+        app.confirm_action();
+        assert_eq!(app.tasks().len(), 2);
+        assert_eq!(app.trash()[0].0.to_string(), "a");
+
+        app.enter_trash_mode();
+        app.restore_from_trash();
+        assert!(app.trash().is_empty());
+        assert_eq!(app.tasks()[2].to_string(), "a");
+        assert!(app.mode() == Mode::Normal);
+
+        // Deletions are saved even when an earlier deletion was undone
+        let dir = std::env::temp_dir().join(format!("kdo-trash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let todo_path = dir.join("todo.txt").to_string_lossy().to_string();
+        let trash_path = dir.join("trash.txt").to_string_lossy().to_string();
+        let mut app = App::new("test", &todo_path, &tasks);
+        app.set_trash_file(&trash_path);
+        for _ in 0..2 {
+            app.task_list.select(Some(1));
+            app.enter_confirm_mode(ConfirmedAction::Delete);
+            app.confirm_action();
+            app.save().unwrap();
+            app.undo();
+        }
+        app.task_list.select(Some(2));
+        app.enter_confirm_mode(ConfirmedAction::Delete);
+        app.confirm_action();
+        app.save().unwrap();
+        let trash = std::fs::read_to_string(&trash_path).unwrap();
+        assert_eq!(trash, "a\na\nb\n");
+
+        // A failed save is reported rather than ending the program
+        let missing_path = dir.join("missing/trash.txt").to_string_lossy().to_string();
+        app.set_trash_file(&missing_path);
+        app.enter_confirm_mode(ConfirmedAction::Save);
+        app.confirm_action();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(app.error_msg().starts_with("Failed to save"));
+        assert!(app.mode() == Mode::Normal);
+
+        // Deleting the last listed task selects the one before it, which can
+        // then be edited
+        let mut app = App::new("test", "todo.txt", &tasks);
+        app.task_list.select(Some(2));
+        app.enter_confirm_mode(ConfirmedAction::Delete);
+        app.confirm_action();
+        assert_eq!(app.get_selected_task().unwrap().to_string(), "a");
+        app.enter_edit_mode();
+        app.exit_edit_mode(Some("c".to_string()));
+        assert_eq!(app.tasks()[1].to_string(), "c");
+    }
+
+    #[test]
+    fn dependencies() {
+        let tasks: Vec<todo_txt::task::Task> = ["a", "b"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // Make "b" depend on "a", which is given an id
+        app.navigate_down();
+        app.navigate_down();
+        app.enter_link_mode();
+        app.toggle_link();
+        app.exit_link_mode();
+        assert_eq!(app.tasks()[1].to_string(), "a id:1");
+        assert_eq!(app.tasks()[2].to_string(), "b dep:1");
+        assert!(app.is_blocked(2));
+
+        app.toggle_hide_blocked();
+        assert_eq!(app.task_list().items(), &[0, 1]);
+
+        // Completing "a" unblocks "b"
+        app.navigate_down();
+        app.navigate_down();
+        assert_eq!(app.get_selected_task().unwrap().to_string(), "a id:1");
+        app.toggle_task_complete();
+        assert!(!app.is_blocked(2));
+    }
+
+    #[test]
+    fn priorities() {
+        let tasks: Vec<todo_txt::task::Task> = ["(A) a", "(Z) z", "(C) c"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // A stays the highest priority, and lowering past Z clears it
+        app.task_list.select(Some(1));
+        app.raise_priority();
+        assert_eq!(app.tasks()[1].priority, Some('A'));
+        app.lower_priority();
+        assert_eq!(app.tasks()[1].priority, Some('B'));
+        app.task_list.select(Some(2));
+        app.lower_priority();
+        assert_eq!(app.tasks()[2].priority, None);
+        app.lower_priority();
+        assert_eq!(app.tasks()[2].priority, None);
+
+        // A task without a priority is raised to the lowest one in use
+        app.raise_priority();
+        assert_eq!(app.tasks()[2].priority, Some('C'));
+
+        app.set_priority(Some('1'));
+        assert_eq!(app.tasks()[2].priority, Some('C'));
+        assert!(!app.error_msg.is_empty());
+        app.set_priority(Some('d'));
+        assert_eq!(app.tasks()[2].priority, Some('D'));
+        app.set_priority(None);
+        assert_eq!(app.tasks()[2].priority, None);
+    }
+
+    #[test]
+    fn picker_months() {
+        let mut app = App::new("test", "todo.txt", &[]);
+        let date = |y, m, d| chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        // The day is clamped to the new month, across years either way, and
+        // regardless of the first day of the week
+        app.set_week_start(chrono::Weekday::Sun);
+        app.picker_date = date(2024, 1, 31);
+        app.move_picker_month(1);
+        assert_eq!(app.picker_date(), date(2024, 2, 29));
+        app.move_picker_month(1);
+        assert_eq!(app.picker_date(), date(2024, 3, 29));
+        app.move_picker_month(-3);
+        assert_eq!(app.picker_date(), date(2023, 12, 29));
+
+        app.picker_date = date(2023, 12, 31);
+        app.move_picker_month(2);
+        assert_eq!(app.picker_date(), date(2024, 2, 29));
+        app.move_picker_month(-14);
+        assert_eq!(app.picker_date(), date(2022, 12, 29));
+        app.move_picker_month(0);
+        assert_eq!(app.picker_date(), date(2022, 12, 29));
+    }
+
+    #[test]
+    fn pick_names() {
+        let tasks: Vec<todo_txt::task::Task> = ["a @home", "b @work +garden"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // Toggling outside pick mode does nothing
+        app.task_list.select(Some(1));
+        app.toggle_picked();
+        assert_eq!(app.tasks()[1].to_string(), "a @home");
+
+        app.enter_pick_mode(PickKind::Context);
+        app.toggle_picked();
+        assert_eq!(app.tasks()[1].to_string(), "a");
+        app.toggle_picked();
+        assert_eq!(app.tasks()[1].to_string(), "a @home");
+        app.pick_list.next();
+        app.toggle_picked();
+        assert_eq!(app.tasks()[1].to_string(), "a @home @work");
+        app.exit_pick_mode();
+
+        app.enter_pick_mode(PickKind::Project);
+        app.toggle_picked();
+        assert_eq!(app.tasks()[1].to_string(), "a @home @work +garden");
+        app.exit_pick_mode();
+
+        // The task stays selected when removing the context it is filtered by
+        app.navigate_right();
+        app.navigate_down();
+        assert_eq!(app.get_selected_context().as_deref(), Some("home"));
+        app.navigate_left();
+        app.navigate_down();
+        app.enter_pick_mode(PickKind::Context);
+        app.toggle_picked();
+        assert_eq!(app.tasks()[1].to_string(), "a @work +garden");
+        assert_eq!(app.get_selected_context(), None);
+        assert_eq!(app.get_selected_task_idx(), Some(1));
+    }
+
+    #[test]
+    fn edit_keeps_selection() {
+        let tasks: Vec<todo_txt::task::Task> = ["(B) a", "b"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // Without a priority selected, a task with a priority stays selected
+        app.navigate_down();
+        assert_eq!(app.get_selected_task().unwrap().to_string(), "(B) a");
+        app.raise_priority();
+        assert_eq!(app.get_selected_task().unwrap().to_string(), "(A) a");
+    }
+
+    #[test]
+    fn subtasks() {
+        let tasks: Vec<todo_txt::task::Task> = ["a", "b", "c"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+        app.set_complete_subtasks(true);
+
+        // Make "c" a subtask of "a", which is given an id
+        app.navigate_up();
+        app.enter_parent_mode();
+        app.choose_parent();
+        assert_eq!(app.tasks()[1].to_string(), "a id:1");
+        assert_eq!(app.tasks()[3].to_string(), "c parent:1");
+
+        app.toggle_outline();
+        assert_eq!(app.task_list().items(), &[0, 1, 3, 2]);
+        assert_eq!(app.task_depth(2), 1);
+
+        // Collapse "a" to hide "c"
+        app.navigate_up();
+        app.toggle_collapse();
+        assert_eq!(app.task_list().items(), &[0, 1, 2]);
+
+        // Completing "a" completes "c"
+        app.toggle_task_complete();
+        assert!(app.tasks()[3].completed);
+    }
+
+    #[test]
+    fn timer() {
+        let tasks: Vec<todo_txt::task::Task> = ["b", "a spent:1h"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+
+        // The timer follows its task when sorting
+        app.navigate_down();
+        app.navigate_down();
+        app.toggle_timer();
+        app.sort_tasks();
+        assert_eq!(app.timer().unwrap().0.description(), "a spent:1h");
+
+        app.navigate_down();
+        app.toggle_timer();
+        assert!(app.timer().is_none());
+        assert_eq!(app.tasks()[1].to_string(), "a spent:1h");
+        assert_eq!(app.sessions().len(), 1);
+        assert_eq!(app.sessions()[0].task, app.tasks()[1]);
+
+        // Undoing a deletion above the timed task moves the timer back with it
+        let tasks: Vec<todo_txt::task::Task> = ["a", "b", "c"]
+            .iter()
+            .map(|line| todo_txt::task::Task::from_str(line).unwrap())
+            .collect();
+        let mut app = App::new("test", "todo.txt", &tasks);
+        app.task_list.select(Some(3));
+        app.toggle_timer();
+        app.task_list.select(Some(1));
+        app.enter_confirm_mode(ConfirmedAction::Delete);
+        app.confirm_action();
+        assert_eq!(app.timer().unwrap().0.description(), "c");
+        app.undo();
+        assert_eq!(app.timer().unwrap().0.description(), "c");
+        app.stop_timer();
+        assert_eq!(app.sessions()[0].task, app.tasks()[3]);
+        assert!(app.tasks()[3].tag("spent").is_some());
+
+        // Undoing stopping the timer resumes it without its session
+        app.undo();
+        assert_eq!(app.timer().unwrap().0.description(), "c");
+        assert!(app.sessions().is_empty());
+    }
+
+    #[test]
+    fn pomodoro_counts() {
+        let tasks = [todo_txt::task::Task::from_str("a").unwrap()];
+        let mut app = App::new("test", "todo.txt", &tasks);
+        let now = chrono::Local::now().naive_local();
+        let minutes = chrono::Duration::minutes;
+
+        // A work period ending while editing is counted after the edit
+        app.task_list.select(Some(1));
+        let start = now - minutes(30);
+        let mut pomodoro = Pomodoro::new(1, start, minutes(25), minutes(25));
+        pomodoro.last_tick = now - chrono::Duration::seconds(1);
+        app.pomodoro = Some(pomodoro);
+        app.enter_edit_mode();
+        assert_eq!(app.tick_pomodoro(), Some(Phase::Work));
+        assert_eq!(app.tasks()[1].to_string(), "a");
+        app.exit_edit_mode(Some("a edited".to_string()));
+        assert_eq!(app.tick_pomodoro(), None);
+        assert_eq!(app.tasks()[1].to_string(), "a edited pomodoros:1");
+
+        // Undo reverts the edit, not the count
+        app.undo();
+        assert_eq!(app.tasks()[1].to_string(), "a pomodoros:1");
+        app.undo();
+        assert_eq!(app.error_msg(), "Nothing to undo");
+    }
+
+    #[test]
+    fn events() {
+        let tasks = [todo_txt::task::Task::from_str("a").unwrap()];
+        let mut app = App::new("test", "todo.txt", &tasks);
+        assert!(!app.handle_event(AppEvent::Tick));
+
+        // The day changing is noticed on the next tick
+        let today = chrono::Local::now().date_naive();
+        app.today = today.pred_opt().unwrap();
+        app.handle_event(AppEvent::Tick);
+        assert_eq!(app.today, today);
+
+        // Only changes made after the app last saved are reported
+        let now = std::time::SystemTime::now();
+        app.saved_at = Some(now);
+        assert!(!app.handle_event(AppEvent::SourceModified(0, now)));
+        assert!(app.error_msg().is_empty());
+        let later = now + std::time::Duration::from_secs(1);
+        assert!(!app.handle_event(AppEvent::SourceModified(1, later)));
+        assert!(app.handle_event(AppEvent::SourceModified(0, later)));
+        assert_eq!(
+            app.error_msg(),
+            "'todo.txt' was modified by another program"
+        );
+
+        // The change is still noted after the message is cleared, and saving
+        // over it must be accepted explicitly
+        app.clear_error();
+        assert_eq!(app.modified_sources(), [0]);
+        assert!(app.confirm_by_default());
+        app.enter_confirm_mode(ConfirmedAction::Save);
+        assert!(!app.confirm_by_default());
+        app.cancel_action();
+        assert_eq!(app.modified_sources(), [0]);
+
+        // Saving overwrites the change, which is then forgotten
+        let path = std::env::temp_dir().join(format!("kdo-events-{}.txt", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut app = App::new("test", &path, &tasks);
+        assert!(app.handle_event(AppEvent::SourceModified(0, now)));
+        app.enter_confirm_mode(ConfirmedAction::Save);
+        app.confirm_action();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved, "a\n");
+        assert!(app.modified_sources().is_empty());
+    }
 }
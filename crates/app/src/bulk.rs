@@ -0,0 +1,53 @@
+/// Commands which can be applied to all marked tasks at once
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BulkCommand {
+    Complete,
+    Delete,
+    SetPriority,
+    AddContext,
+    RemoveContext,
+    AddProject,
+    RemoveProject,
+    MoveToFile,
+}
+
+impl BulkCommand {
+    /// All commands, in menu order
+    pub const ALL: [BulkCommand; 8] = [
+        BulkCommand::Complete,
+        BulkCommand::Delete,
+        BulkCommand::SetPriority,
+        BulkCommand::AddContext,
+        BulkCommand::RemoveContext,
+        BulkCommand::AddProject,
+        BulkCommand::RemoveProject,
+        BulkCommand::MoveToFile,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BulkCommand::Complete => "complete",
+            BulkCommand::Delete => "delete",
+            BulkCommand::SetPriority => "set priority",
+            BulkCommand::AddContext => "add context",
+            BulkCommand::RemoveContext => "remove context",
+            BulkCommand::AddProject => "add project",
+            BulkCommand::RemoveProject => "remove project",
+            BulkCommand::MoveToFile => "move to file",
+        }
+    }
+}
+
+/// A bulk command together with its chosen argument, awaiting confirmation
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BulkAction {
+    Complete,
+    Delete,
+    SetPriority(Option<char>),
+    AddContext(String),
+    RemoveContext(String),
+    AddProject(String),
+    RemoveProject(String),
+    /// Index into the app's sources
+    MoveToFile(usize),
+}
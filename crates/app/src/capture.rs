@@ -0,0 +1,68 @@
+use std::str::FromStr;
+use todo_txt::task::Task;
+
+/// Build a task from quickly captured text.  Words of `defaults` (eg,
+/// `+inbox`) which the text lacks are appended, relative dates are normalized
+/// and the creation date is set to `today` unless given.
+pub fn capture_task(
+    text: &str,
+    defaults: &str,
+    today: chrono::NaiveDate,
+    week_start: chrono::Weekday,
+) -> Option<Task> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    for word in defaults.split_whitespace() {
+        if !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    let line = todo_txt::date::normalize_dates(&words.join(" "), today, week_start);
+
+    let mut task = Task::from_str(&line).ok()?;
+    if task.date_created.is_none() {
+        task.date_created = Some(today);
+    }
+    Some(task)
+}
+
+/// Contexts and projects among the capture defaults.  Tasks with no other
+/// contexts or projects are shown in the inbox.
+pub fn inbox_names(defaults: &str) -> (Vec<String>, Vec<String>) {
+    let words = defaults.split_whitespace();
+    let contexts = words
+        .clone()
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(String::from)
+        .collect();
+    let projects = words
+        .filter_map(|word| word.strip_prefix('+'))
+        .map(String::from)
+        .collect();
+    (contexts, projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture() {
+        let today = chrono::NaiveDate::from_ymd_opt(2023, 11, 30).unwrap();
+        let task = capture_task(
+            "call bob due:tom +inbox",
+            "+inbox @phone",
+            today,
+            chrono::Weekday::Mon,
+        );
+        assert_eq!(
+            task.unwrap().to_string(),
+            "2023-11-30 call bob due:2023-12-01 +inbox @phone"
+        );
+        assert!(capture_task("  ", "", today, chrono::Weekday::Mon).is_none());
+
+        assert_eq!(
+            inbox_names("+inbox @phone t:today"),
+            (vec!["phone".to_string()], vec!["inbox".to_string()])
+        );
+    }
+}
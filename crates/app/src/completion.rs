@@ -0,0 +1,108 @@
+/// Maximum number of completion candidates returned by [complete]
+pub const MAX_COMPLETIONS: usize = 8;
+
+/// Score how well `pattern` fuzzily matches `candidate`.  All characters of the
+/// pattern must appear in the candidate in order (ignoring case).  Matches at the
+/// start of the candidate, after a separator, or directly following the
+/// previous match score higher.  Returns `None` if there is no match.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut candidate_chars = candidate.chars().enumerate().peekable();
+    let mut prev_match: Option<usize> = None;
+    let mut prev_char: Option<char> = None;
+
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (i, c) = candidate_chars.next()?;
+            let is_match = c.to_lowercase().eq(std::iter::once(p));
+            let after_separator = prev_char.map_or(true, |prev| !prev.is_alphanumeric());
+            prev_char = Some(c);
+            if is_match {
+                score += 1;
+                if i == 0 {
+                    score += 8;
+                } else if after_separator {
+                    score += 4;
+                }
+                if prev_match.is_some_and(|prev| prev + 1 == i) {
+                    score += 4;
+                }
+                prev_match = Some(i);
+                break;
+            }
+        }
+    }
+
+    // Prefer shorter candidates when scores are otherwise equal
+    Some(score * 256 - candidate.chars().count().min(255) as i64)
+}
+
+/// Completion candidates for the word being typed.  Words starting with `@` or
+/// `+` complete to contexts or projects, other words complete to tag keys
+/// (including the trailing colon).  Words which already contain a colon are not
+/// completed.  Candidates are ordered best match first.
+pub fn complete(
+    word: &str,
+    contexts: &[String],
+    projects: &[String],
+    tag_keys: &[String],
+) -> Vec<String> {
+    let (prefix, pattern, names, suffix) = if let Some(pattern) = word.strip_prefix('@') {
+        ("@", pattern, contexts, "")
+    } else if let Some(pattern) = word.strip_prefix('+') {
+        ("+", pattern, projects, "")
+    } else if !word.is_empty() && !word.contains(':') {
+        ("", word, tag_keys, ":")
+    } else {
+        return Vec::new();
+    };
+
+    let mut scored: Vec<(i64, &String)> = names
+        .iter()
+        .filter(|name| name.as_str() != pattern)
+        .filter_map(|name| fuzzy_score(pattern, name).map(|score| (score, name)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    scored
+        .into_iter()
+        .take(MAX_COMPLETIONS)
+        .map(|(_, name)| format!("{}{}{}", prefix, name, suffix))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn fuzzy() {
+        assert!(fuzzy_score("wba", "work.backend.api").is_some());
+        assert!(fuzzy_score("abw", "work.backend.api").is_none());
+        assert!(fuzzy_score("WO", "work").is_some());
+        assert!(fuzzy_score("wo", "work") > fuzzy_score("wo", "hardwood"));
+        assert!(fuzzy_score("wo", "work") > fuzzy_score("wo", "workshop"));
+    }
+
+    #[test]
+    fn candidates() {
+        let contexts = strings(&["home", "office", "phone"]);
+        let projects = strings(&["work.api", "work.docs", "garden"]);
+        let tag_keys = strings(&["due", "t", "id"]);
+
+        assert_eq!(
+            complete("@ho", &contexts, &projects, &tag_keys),
+            ["@home", "@phone"]
+        );
+        assert_eq!(
+            complete("+wd", &contexts, &projects, &tag_keys),
+            ["+work.docs"]
+        );
+        assert_eq!(complete("du", &contexts, &projects, &tag_keys), ["due:"]);
+        assert!(complete("due:", &contexts, &projects, &tag_keys).is_empty());
+        assert!(complete("", &contexts, &projects, &tag_keys).is_empty());
+    }
+}
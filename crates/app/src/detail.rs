@@ -0,0 +1,258 @@
+use todo_txt::task::Task;
+
+/// Parsed fields of a task shown in the detail pane
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TaskField {
+    Description,
+    Completion,
+    Priority,
+    Created,
+    Completed,
+    Age,
+    Due,
+    Contexts,
+    Projects,
+    Tags,
+}
+
+impl TaskField {
+    /// All fields, in display order
+    pub const ALL: [TaskField; 10] = [
+        TaskField::Description,
+        TaskField::Completion,
+        TaskField::Priority,
+        TaskField::Created,
+        TaskField::Completed,
+        TaskField::Age,
+        TaskField::Due,
+        TaskField::Contexts,
+        TaskField::Projects,
+        TaskField::Tags,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskField::Description => "description",
+            TaskField::Completion => "complete",
+            TaskField::Priority => "priority",
+            TaskField::Created => "created",
+            TaskField::Completed => "completed",
+            TaskField::Age => "age",
+            TaskField::Due => "due",
+            TaskField::Contexts => "contexts",
+            TaskField::Projects => "projects",
+            TaskField::Tags => "tags",
+        }
+    }
+
+    /// Fields which are computed from other fields can not be edited
+    pub fn is_editable(&self) -> bool {
+        *self != TaskField::Age
+    }
+
+    /// Fields which are edited by toggling rather than entering text
+    pub fn is_toggle(&self) -> bool {
+        *self == TaskField::Completion
+    }
+
+    /// Display string for the field, including computed age and due status
+    pub fn display(&self, task: &Task, today: chrono::NaiveDate) -> String {
+        match self {
+            TaskField::Age => match task.date_created {
+                Some(created) => days_str((today - created).num_days()),
+                None => "-".to_string(),
+            },
+            TaskField::Due => match task.due_date() {
+                Some(due) if task.completed => due.to_string(),
+                Some(due) => {
+                    let days = (due - today).num_days();
+                    let status = match days {
+                        0 => "due today".to_string(),
+                        d if d < 0 => format!("overdue by {}", days_str(-d)),
+                        d => format!("due in {}", days_str(d)),
+                    };
+                    format!("{} ({})", due, status)
+                }
+                None => "-".to_string(),
+            },
+            TaskField::Completion => {
+                if task.completed {
+                    "yes".to_string()
+                } else {
+                    "no".to_string()
+                }
+            }
+            _ => {
+                let value = self.value(task);
+                if value.is_empty() {
+                    "-".to_string()
+                } else {
+                    value
+                }
+            }
+        }
+    }
+
+    /// Editable string for the field.  See [Self::apply]
+    pub fn value(&self, task: &Task) -> String {
+        match self {
+            TaskField::Description => task.description().to_string(),
+            TaskField::Completion => task.completed.to_string(),
+            TaskField::Priority => task.priority.map(String::from).unwrap_or_default(),
+            TaskField::Created => date_str(task.date_created),
+            TaskField::Completed => date_str(task.date_completed),
+            TaskField::Age => String::new(),
+            TaskField::Due => task.tag("due").unwrap_or_default().to_string(),
+            TaskField::Contexts => task.contexts().join(" "),
+            TaskField::Projects => task.projects().join(" "),
+            TaskField::Tags => task
+                .tags()
+                .iter()
+                .map(|(key, value)| format!("{}:{}", key, value))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Set the field from an edited string, as returned by [Self::value].  Lists of
+    /// contexts, projects and tags are separated by whitespace or commas and may
+    /// be given with or without their `@`/`+` prefix.  Empty strings clear
+    /// optional fields.  Only completed tasks with a creation date may have a
+    /// completion date, as the format can not be read back otherwise.
+    pub fn apply(&self, task: &mut Task, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let words: Vec<&str> = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .collect();
+        match self {
+            TaskField::Description => {
+                if value.is_empty() {
+                    return Err("Description can not be empty".to_string());
+                }
+                task.set_description(value);
+            }
+            TaskField::Completion => {
+                task.completed = matches!(value, "true" | "yes" | "y" | "x");
+                if !task.completed {
+                    task.date_completed = None;
+                }
+            }
+            TaskField::Priority => {
+                task.priority = match value.chars().collect::<Vec<_>>()[..] {
+                    [] => None,
+                    [p] if p.is_ascii_alphabetic() => Some(p.to_ascii_uppercase()),
+                    _ => return Err(format!("Invalid priority '{}'", value)),
+                };
+            }
+            TaskField::Created => {
+                let created = parse_date(value)?;
+                if created.is_none() && task.date_completed.is_some() {
+                    return Err("Clear the completion date first".to_string());
+                }
+                task.date_created = created;
+            }
+            TaskField::Completed => {
+                let completed = parse_date(value)?;
+                if completed.is_some() && !task.completed {
+                    return Err("Only completed tasks have a completion date".to_string());
+                }
+                if completed.is_some() && task.date_created.is_none() {
+                    return Err("Set the creation date first".to_string());
+                }
+                task.date_completed = completed;
+            }
+            TaskField::Age => return Err("Age can not be edited".to_string()),
+            TaskField::Due => {
+                let due = parse_date(value)?.map(|due| due.to_string());
+                task.set_tag("due", due.as_deref());
+            }
+            TaskField::Contexts => {
+                let contexts: Vec<String> = words
+                    .iter()
+                    .map(|w| w.trim_start_matches('@').to_string())
+                    .collect();
+                task.set_contexts(&contexts);
+            }
+            TaskField::Projects => {
+                let projects: Vec<String> = words
+                    .iter()
+                    .map(|w| w.trim_start_matches('+').to_string())
+                    .collect();
+                task.set_projects(&projects);
+            }
+            TaskField::Tags => {
+                let mut tags = Vec::new();
+                for word in words {
+                    match todo_txt::parse::split_tag(word) {
+                        Some((key, value)) => tags.push((key.to_string(), value.to_string())),
+                        None => return Err(format!("Invalid tag '{}'", word)),
+                    }
+                }
+                task.set_tags(&tags);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn date_str(date: Option<chrono::NaiveDate>) -> String {
+    date.map(|date| date.to_string()).unwrap_or_default()
+}
+
+fn days_str(days: i64) -> String {
+    if days == 1 {
+        "1 day".to_string()
+    } else {
+        format!("{} days", days)
+    }
+}
+
+fn parse_date(value: &str) -> Result<Option<chrono::NaiveDate>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn apply_fields() {
+        let mut task = Task::from_str("2023-11-01 write docs @desk +work").unwrap();
+        TaskField::Priority.apply(&mut task, "b").unwrap();
+        TaskField::Contexts
+            .apply(&mut task, "@office, home")
+            .unwrap();
+        TaskField::Due.apply(&mut task, "2023-12-01").unwrap();
+        assert!(TaskField::Created.apply(&mut task, "yesterday").is_err());
+        assert!(TaskField::Completed.apply(&mut task, "2023-12-04").is_err());
+        assert_eq!(
+            task.to_string(),
+            "(B) 2023-11-01 write docs +work @office @home due:2023-12-01"
+        );
+
+        let today = chrono::NaiveDate::from_ymd_opt(2023, 12, 3).unwrap();
+        assert_eq!(TaskField::Age.display(&task, today), "32 days");
+        assert_eq!(
+            TaskField::Due.display(&task, today),
+            "2023-12-01 (overdue by 2 days)"
+        );
+
+        // Dates are only changed when the task reads back the same
+        let mut task = Task::from_str("x 2023-12-04 2023-11-01 ship").unwrap();
+        assert!(TaskField::Created.apply(&mut task, "").is_err());
+        TaskField::Completed.apply(&mut task, "").unwrap();
+        TaskField::Created.apply(&mut task, "").unwrap();
+        assert!(TaskField::Completed.apply(&mut task, "2023-12-04").is_err());
+        assert_eq!(task.to_string(), "x ship");
+        TaskField::Created.apply(&mut task, "2023-11-02").unwrap();
+        TaskField::Completed.apply(&mut task, "2023-12-05").unwrap();
+        assert_eq!(Task::from_str(&task.to_string()), Ok(task));
+    }
+}
@@ -0,0 +1,103 @@
+/// Change to one line of a text edited outside of kdo
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LineChange {
+    /// Original line at the index was replaced by the given line
+    Edit(usize, String),
+    /// Original line at the index was removed
+    Delete(usize),
+    /// Line was added
+    Add(String),
+}
+
+/// Compare edited lines against the original lines.  Lines common to both (by
+/// longest common subsequence) are unchanged.  Between common lines, removed
+/// and added lines are paired in order as edits, and any left over are
+/// deletions or additions.
+pub fn diff_lines(original: &[String], edited: &[String]) -> Vec<LineChange> {
+    // Skip common prefix and suffix to keep the table small for typical edits
+    let prefix = original
+        .iter()
+        .zip(edited)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(edited[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &original[prefix..original.len() - suffix];
+    let b = &edited[prefix..edited.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..], b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut deleted, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            flush_hunk(&mut changes, &mut deleted, &mut added);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            deleted.push(prefix + i);
+            i += 1;
+        } else {
+            added.push(b[j].clone());
+            j += 1;
+        }
+    }
+    flush_hunk(&mut changes, &mut deleted, &mut added);
+    changes
+}
+
+/// Pair the removed and added lines between two common lines
+fn flush_hunk(changes: &mut Vec<LineChange>, deleted: &mut Vec<usize>, added: &mut Vec<String>) {
+    let mut added_iter = added.drain(..);
+    for idx in deleted.drain(..) {
+        match added_iter.next() {
+            Some(line) => changes.push(LineChange::Edit(idx, line)),
+            None => changes.push(LineChange::Delete(idx)),
+        }
+    }
+    changes.extend(added_iter.map(LineChange::Add));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn diff() {
+        let original = strings(&["a", "b", "c", "d", "e"]);
+        let edited = strings(&["a", "B", "c", "e", "f", "g"]);
+        assert_eq!(
+            diff_lines(&original, &edited),
+            [
+                LineChange::Edit(1, "B".to_string()),
+                LineChange::Delete(3),
+                LineChange::Add("f".to_string()),
+                LineChange::Add("g".to_string()),
+            ]
+        );
+        assert!(diff_lines(&original, &original).is_empty());
+        assert_eq!(
+            diff_lines(&[], &strings(&["new"])),
+            [LineChange::Add("new".to_string())]
+        );
+    }
+}
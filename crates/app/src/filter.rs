@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use todo_txt::task::Task;
+
+/// Tasks passing the filters which don't depend on the side panes, indexed by
+/// context, project and priority so changing the side pane selections only
+/// visits the tasks selected rather than filtering all tasks again
+#[derive(Debug, Default)]
+pub struct FilterIndex {
+    /// Sorted indices of the indexed tasks
+    tasks: Vec<usize>,
+    /// Sorted indices of the tasks within each context, ie, in the context
+    /// itself or one of its subcontexts
+    contexts: HashMap<String, Vec<usize>>,
+    /// Sorted indices of the tasks within each project
+    projects: HashMap<String, Vec<usize>>,
+    /// Sorted indices of the tasks with each priority
+    priorities: HashMap<char, Vec<usize>>,
+}
+
+impl FilterIndex {
+    /// Index the given tasks, each paired with its index into the task list
+    pub fn new<'a>(tasks: impl IntoIterator<Item = (usize, &'a Task)>) -> FilterIndex {
+        let mut index = FilterIndex::default();
+        for (idx, task) in tasks {
+            index.insert(idx, task);
+        }
+        index
+    }
+
+    /// Sorted indices of all the indexed tasks
+    pub fn tasks(&self) -> &[usize] {
+        &self.tasks
+    }
+
+    /// Add the task at `idx`, unless it's already indexed
+    pub fn insert(&mut self, idx: usize, task: &Task) {
+        let Err(position) = self.tasks.binary_search(&idx) else {
+            return;
+        };
+        self.tasks.insert(position, idx);
+        for context in task.contexts() {
+            for name in within(context) {
+                insert_sorted(self.contexts.entry(name.to_string()).or_default(), idx);
+            }
+        }
+        for project in task.projects() {
+            for name in within(project) {
+                insert_sorted(self.projects.entry(name.to_string()).or_default(), idx);
+            }
+        }
+        if let Some(priority) = task.priority {
+            insert_sorted(self.priorities.entry(priority).or_default(), idx);
+        }
+    }
+
+    /// Remove the task at `idx`, if indexed
+    pub fn remove(&mut self, idx: usize) {
+        let Ok(position) = self.tasks.binary_search(&idx) else {
+            return;
+        };
+        self.tasks.remove(position);
+        let remove = |indices: &mut Vec<usize>| {
+            if let Ok(position) = indices.binary_search(&idx) {
+                indices.remove(position);
+            }
+            !indices.is_empty()
+        };
+        self.contexts.retain(|_, indices| remove(indices));
+        self.projects.retain(|_, indices| remove(indices));
+        self.priorities.retain(|_, indices| remove(indices));
+    }
+
+    /// Sorted indices of the indexed tasks within the given context and
+    /// project and with the given priority, where given
+    pub fn select(
+        &self,
+        context: Option<&str>,
+        project: Option<&str>,
+        priority: Option<char>,
+    ) -> Vec<usize> {
+        let mut selected = Vec::new();
+        if let Some(context) = context {
+            selected.push(self.contexts.get(context).map_or(&[][..], Vec::as_slice));
+        }
+        if let Some(project) = project {
+            selected.push(self.projects.get(project).map_or(&[][..], Vec::as_slice));
+        }
+        if let Some(priority) = priority {
+            selected.push(
+                self.priorities
+                    .get(&priority)
+                    .map_or(&[][..], Vec::as_slice),
+            );
+        }
+
+        // Check each task of the shortest list against the others
+        selected.sort_by_key(|indices| indices.len());
+        let Some((shortest, others)) = selected.split_first() else {
+            return self.tasks.clone();
+        };
+        shortest
+            .iter()
+            .copied()
+            .filter(|idx| {
+                others
+                    .iter()
+                    .all(|indices| indices.binary_search(idx).is_ok())
+            })
+            .collect()
+    }
+}
+
+/// The name and each of its ancestors, eg, "a.b" and "a" for "a.b"
+fn within(name: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(name), |name| todo_txt::tasks::parent(name))
+}
+
+fn insert_sorted(indices: &mut Vec<usize>, idx: usize) {
+    if let Err(position) = indices.binary_search(&idx) {
+        indices.insert(position, idx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn select() {
+        let tasks: Vec<Task> = [
+            "(A) a @home.desk +garden",
+            "b @home",
+            "(A) c @work +garden",
+            "(B) d @home +garden",
+        ]
+        .iter()
+        .map(|line| Task::from_str(line).unwrap())
+        .collect();
+        let mut index = FilterIndex::new(tasks.iter().enumerate().skip(1));
+
+        assert_eq!(index.tasks(), [1, 2, 3]);
+        assert_eq!(index.select(None, None, None), [1, 2, 3]);
+        assert_eq!(index.select(Some("home"), None, None), [1, 3]);
+        assert_eq!(index.select(Some("home"), Some("garden"), None), [3]);
+        assert_eq!(index.select(None, Some("garden"), Some('A')), [2]);
+        assert!(index.select(Some("home.desk"), None, None).is_empty());
+        assert!(index.select(Some("office"), None, None).is_empty());
+
+        // Tasks are added and removed from the lists of everything they're in
+        index.insert(0, &tasks[0]);
+        assert_eq!(index.select(Some("home"), None, None), [0, 1, 3]);
+        assert_eq!(index.select(Some("home.desk"), None, Some('A')), [0]);
+        index.remove(3);
+        assert_eq!(index.tasks(), [0, 1, 2]);
+        assert_eq!(index.select(Some("home"), Some("garden"), None), [0]);
+        assert!(index.select(None, None, Some('B')).is_empty());
+    }
+}
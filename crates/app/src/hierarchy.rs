@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+
+/// A collapsible tree of nested project or context names, eg, `work`,
+/// `work.backend`, `work.backend.api`.  Names are stored in tree order so the
+/// visible names can be displayed directly as an indented list.
+#[derive(Debug, Default)]
+pub struct Hierarchy {
+    names: Vec<String>,
+    /// Position of each name in the tree, worked out once when the names are
+    /// set rather than for every label drawn
+    nodes: HashMap<String, Node>,
+    /// Whether any name is nested, so labels need indenting
+    nested: bool,
+    collapsed: HashSet<String>,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct Node {
+    depth: usize,
+    has_children: bool,
+}
+
+impl Hierarchy {
+    /// Create a fully expanded hierarchy from a list of (possibly nested) names.
+    /// Implied ancestors of each name are added to the tree.
+    pub fn new(names: &[String]) -> Hierarchy {
+        let mut hierarchy = Hierarchy::default();
+        hierarchy.set_names(names);
+        hierarchy
+    }
+
+    /// Replace the names in the tree, keeping collapsed nodes which still exist
+    pub fn set_names(&mut self, names: &[String]) {
+        self.names = todo_txt::tasks::with_ancestors(names);
+        self.nodes = self
+            .names
+            .iter()
+            .map(|name| {
+                let node = Node {
+                    depth: todo_txt::tasks::depth(name),
+                    has_children: false,
+                };
+                (name.clone(), node)
+            })
+            .collect();
+        for name in &self.names {
+            if let Some(parent) = todo_txt::tasks::parent(name) {
+                if let Some(node) = self.nodes.get_mut(parent) {
+                    node.has_children = true;
+                }
+            }
+        }
+        self.nested = self.nodes.values().any(|node| node.depth > 0);
+        let nodes = &self.nodes;
+        self.collapsed.retain(|name| nodes.contains_key(name));
+    }
+
+    /// All names in the tree, whether visible or not
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Names which are not hidden beneath a collapsed ancestor
+    pub fn visible(&self) -> Vec<String> {
+        self.names
+            .iter()
+            .filter(|name| {
+                !std::iter::successors(todo_txt::tasks::parent(name), |name| {
+                    todo_txt::tasks::parent(name)
+                })
+                .any(|ancestor| self.collapsed.contains(ancestor))
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn has_children(&self, name: &str) -> bool {
+        self.nodes.get(name).is_some_and(|node| node.has_children)
+    }
+
+    pub fn is_collapsed(&self, name: &str) -> bool {
+        self.collapsed.contains(name)
+    }
+
+    /// Collapse or expand the node `name`.  Leaf nodes can not be collapsed.
+    pub fn toggle(&mut self, name: &str) {
+        if !self.collapsed.remove(name) && self.has_children(name) {
+            self.collapsed.insert(name.to_string());
+        }
+    }
+
+    /// Indented display string for a node, with a marker showing whether it is
+    /// expanded or collapsed
+    pub fn label(&self, name: &str) -> String {
+        if !self.nested {
+            return name.to_string();
+        }
+
+        let node = self.nodes.get(name).copied().unwrap_or_default();
+        let marker = if !node.has_children {
+            "  "
+        } else if self.is_collapsed(name) {
+            "▸ "
+        } else {
+            "▾ "
+        };
+        format!(
+            "{:indent$}{}{}",
+            "",
+            marker,
+            todo_txt::tasks::leaf(name),
+            indent = 2 * node.depth
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn tree() {
+        let mut tree = Hierarchy::new(&names(&["work.backend.api", "home", ".garden"]));
+        assert_eq!(
+            tree.names(),
+            [
+                ".garden",
+                "home",
+                "work",
+                "work.backend",
+                "work.backend.api"
+            ]
+        );
+        assert_eq!(tree.label(".garden"), "  garden");
+        assert_eq!(tree.label("work"), "▾ work");
+        assert_eq!(tree.label("work.backend.api"), "      api");
+
+        // Collapsing hides descendants, and is kept while the node exists
+        tree.toggle("work.backend");
+        tree.toggle("home");
+        assert_eq!(tree.label("work.backend"), "  ▸ backend");
+        assert_eq!(tree.visible(), [".garden", "home", "work", "work.backend"]);
+        tree.set_names(&names(&["work.backend.db", "home"]));
+        assert_eq!(tree.visible(), ["home", "work", "work.backend"]);
+        tree.set_names(&names(&["work", "home"]));
+        assert!(!tree.is_collapsed("work.backend"));
+        assert_eq!(tree.label("work"), "work");
+    }
+}
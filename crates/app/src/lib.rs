@@ -1,4 +1,14 @@
 pub mod app;
+pub mod bulk;
+pub mod capture;
+pub mod completion;
+pub mod detail;
+pub mod external;
+pub mod filter;
+pub mod hierarchy;
+pub mod pomodoro;
+pub mod report;
 pub mod selection_list;
+pub mod timelog;
 
 pub use app::*;
@@ -0,0 +1,144 @@
+use chrono::NaiveDateTime;
+
+/// Tag counting the work periods completed on a task, eg, `pomodoros:3`
+pub const POMODORO_TAG_KEY: &str = "pomodoros";
+
+/// Longest time between ticks, in seconds, for which the pomodoro is taken to
+/// have kept running.  Longer gaps happen when the computer is suspended.
+const MAX_TICK_GAP_SECONDS: i64 = 60;
+
+/// Part of the pomodoro cycle, alternating between work and breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+/// A pomodoro timer cycling between work periods and breaks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pomodoro {
+    /// Index into the app's tasks of the task worked on
+    pub task_idx: usize,
+    /// When the first work period started, telling pomodoros apart
+    pub start: NaiveDateTime,
+    pub phase: Phase,
+    pub phase_start: NaiveDateTime,
+    /// When the pomodoro was last ticked
+    pub last_tick: NaiveDateTime,
+    pub work_length: chrono::Duration,
+    pub break_length: chrono::Duration,
+}
+
+impl Pomodoro {
+    /// Start a work period on the task at `task_idx`
+    pub fn new(
+        task_idx: usize,
+        start: NaiveDateTime,
+        work_length: chrono::Duration,
+        break_length: chrono::Duration,
+    ) -> Pomodoro {
+        Pomodoro {
+            task_idx,
+            start,
+            phase: Phase::Work,
+            phase_start: start,
+            last_tick: start,
+            work_length,
+            break_length,
+        }
+    }
+
+    fn phase_length(&self) -> chrono::Duration {
+        match self.phase {
+            Phase::Work => self.work_length,
+            Phase::Break => self.break_length,
+        }
+    }
+
+    /// End of the current phase, or None if too far off to represent
+    fn phase_end(&self) -> Option<NaiveDateTime> {
+        self.phase_start.checked_add_signed(self.phase_length())
+    }
+
+    /// Time left in the current phase at `now`
+    pub fn remaining(&self, now: NaiveDateTime) -> chrono::Duration {
+        match self.phase_end() {
+            Some(end) => (end - now).max(chrono::Duration::zero()),
+            None => self.phase_length(),
+        }
+    }
+
+    /// Move on to the phase under way at `now`, returning the last phase which
+    /// ended, if any, and the number of work periods completed.  Work periods
+    /// which ended while no ticks arrived, eg, while the computer was
+    /// suspended, are not counted.
+    pub fn tick(&mut self, now: NaiveDateTime) -> Option<(Phase, u32)> {
+        let running = now - self.last_tick <= chrono::Duration::seconds(MAX_TICK_GAP_SECONDS);
+        self.last_tick = now;
+        let mut ended = None;
+        let mut work_periods = 0;
+        while let Some(end) = self.phase_end().filter(|end| *end <= now) {
+            if self.phase == Phase::Work && running {
+                work_periods += 1;
+            }
+            ended = Some(self.phase);
+            self.phase = match self.phase {
+                Phase::Work => Phase::Break,
+                Phase::Break => Phase::Work,
+            };
+            self.phase_start = end;
+        }
+        ended.map(|phase| (phase, work_periods))
+    }
+}
+
+/// Parse work and break lengths given as `WORK/BREAK`, eg, `25m/5m`
+pub fn parse_lengths(s: &str) -> Option<(chrono::Duration, chrono::Duration)> {
+    let (work, rest) = s.split_once('/')?;
+    let work = todo_txt::date::parse_duration(work)?;
+    let rest = todo_txt::date::parse_duration(rest)?;
+    (work > chrono::Duration::zero() && rest > chrono::Duration::zero()).then_some((work, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle() {
+        let start = chrono::NaiveDate::from_ymd_opt(2023, 11, 30)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let minutes = chrono::Duration::minutes;
+        let seconds = chrono::Duration::seconds;
+        let mut pomodoro = Pomodoro::new(1, start, minutes(25), minutes(5));
+
+        assert_eq!(pomodoro.remaining(start + minutes(10)), minutes(15));
+        assert_eq!(pomodoro.tick(start + minutes(10)), None);
+        pomodoro.tick(start + minutes(25) - seconds(1));
+        assert_eq!(pomodoro.tick(start + minutes(25)), Some((Phase::Work, 1)));
+        assert_eq!(pomodoro.phase, Phase::Break);
+        assert_eq!(pomodoro.remaining(start + minutes(26)), minutes(4));
+        pomodoro.tick(start + minutes(30) - seconds(1));
+        assert_eq!(pomodoro.tick(start + minutes(30)), Some((Phase::Break, 0)));
+        assert_eq!(pomodoro.phase, Phase::Work);
+
+        // After a gap in the ticks the cycle catches up at once, without
+        // counting the work periods missed
+        let later = start + minutes(30 + 2 * 30 + 26);
+        assert_eq!(pomodoro.tick(later), Some((Phase::Work, 0)));
+        assert_eq!(pomodoro.phase, Phase::Break);
+        assert_eq!(pomodoro.remaining(later), minutes(4));
+        assert_eq!(pomodoro.tick(later + seconds(1)), None);
+
+        // Lengths too long to represent never end
+        let (work_length, break_length) = parse_lengths("2562047788015h/5m").unwrap();
+        let mut pomodoro = Pomodoro::new(1, start, work_length, break_length);
+        assert_eq!(pomodoro.tick(start + minutes(30)), None);
+
+        assert_eq!(parse_lengths("50m/10m"), Some((minutes(50), minutes(10))));
+        assert_eq!(parse_lengths("50m"), None);
+        assert_eq!(parse_lengths("0m/5m"), None);
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{NaiveDate, Weekday};
+use std::borrow::Borrow;
+use todo_txt::task::Task;
+
+/// Number of days shown in the daily completion history
+pub const REPORT_DAYS: usize = 30;
+/// Number of weeks shown in the weekly completion history and project burndowns
+pub const REPORT_WEEKS: usize = 12;
+/// Maximum number of contexts shown in the top contexts chart
+pub const REPORT_TOP_CONTEXTS: usize = 8;
+
+/// Open task counts for one project over the report weeks, oldest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Burndown {
+    pub project: String,
+    pub open: Vec<u64>,
+}
+
+/// Summary statistics used for reviewing a task list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Last day covered by the report
+    pub today: NaiveDate,
+    /// Tasks completed on each of the last [REPORT_DAYS] days, oldest first
+    pub completed_per_day: Vec<u64>,
+    /// Start date and number of tasks completed for each of the last
+    /// [REPORT_WEEKS] weeks, oldest first
+    pub completed_per_week: Vec<(NaiveDate, u64)>,
+    /// Average number of days since creation of open tasks with a creation date
+    pub average_open_age: Option<f64>,
+    /// Number of open tasks at the end of each report week, per project.  Only
+    /// projects with open tasks during the report period are included.
+    pub burndowns: Vec<Burndown>,
+    /// Contexts with the most open tasks, most first
+    pub top_contexts: Vec<(String, u64)>,
+}
+
+impl Report {
+    pub fn new<T: Borrow<Task>>(tasks: &[T], today: NaiveDate, week_start: Weekday) -> Report {
+        let tasks: Vec<&Task> = tasks.iter().map(|task| task.borrow()).collect();
+
+        let completed_per_day = (0..REPORT_DAYS)
+            .rev()
+            .map(|days_ago| {
+                let day = today - chrono::Duration::days(days_ago as i64);
+                tasks
+                    .iter()
+                    .filter(|task| task.completed && task.date_completed == Some(day))
+                    .count() as u64
+            })
+            .collect();
+
+        let week_ends = week_ends(today, week_start);
+        let completed_per_week = week_ends
+            .iter()
+            .map(|week_end| {
+                let week_start = *week_end - chrono::Duration::days(6);
+                let count = tasks
+                    .iter()
+                    .filter(|task| {
+                        task.completed
+                            && task
+                                .date_completed
+                                .is_some_and(|date| date >= week_start && date <= *week_end)
+                    })
+                    .count() as u64;
+                (week_start, count)
+            })
+            .collect();
+
+        let ages: Vec<i64> = tasks
+            .iter()
+            .filter(|task| !task.completed)
+            .filter_map(|task| task.date_created)
+            .map(|created| (today - created).num_days())
+            .collect();
+        let average_open_age = if ages.is_empty() {
+            None
+        } else {
+            Some(ages.iter().sum::<i64>() as f64 / ages.len() as f64)
+        };
+
+        let burndowns = todo_txt::tasks::collect_projects(&tasks, true)
+            .into_iter()
+            .map(|project| {
+                let project_tasks = todo_txt::tasks::with_project(&tasks, &project);
+                let open = week_ends
+                    .iter()
+                    .map(|week_end| {
+                        project_tasks
+                            .iter()
+                            .filter(|task| is_open_on(task, *week_end))
+                            .count() as u64
+                    })
+                    .collect();
+                Burndown { project, open }
+            })
+            .filter(|burndown| burndown.open.iter().any(|open| *open > 0))
+            .collect();
+
+        let mut top_contexts: Vec<(String, u64)> = todo_txt::tasks::count_contexts(&tasks, today)
+            .into_iter()
+            .filter(|(_, counts)| counts.open > 0)
+            .map(|(context, counts)| (context, counts.open as u64))
+            .collect();
+        top_contexts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_contexts.truncate(REPORT_TOP_CONTEXTS);
+
+        Report {
+            today,
+            completed_per_day,
+            completed_per_week,
+            average_open_age,
+            burndowns,
+            top_contexts,
+        }
+    }
+}
+
+/// Last day of each of the report weeks ending with the week containing
+/// `today`, oldest first
+fn week_ends(today: NaiveDate, week_start: Weekday) -> Vec<NaiveDate> {
+    let this_week_end =
+        todo_txt::date::start_of_week(today, week_start) + chrono::Duration::days(6);
+    (0..REPORT_WEEKS)
+        .rev()
+        .map(|weeks_ago| this_week_end - chrono::Duration::weeks(weeks_ago as i64))
+        .collect()
+}
+
+/// Returns true if a task was open at the end of `day`.  Tasks without a
+/// creation date are assumed to have always existed, and completed tasks without
+/// a completion date are assumed to have always been complete.
+fn is_open_on(task: &Task, day: NaiveDate) -> bool {
+    let created = task.date_created.map_or(true, |created| created <= day);
+    let completed = task.completed && task.date_completed.map_or(true, |done| done <= day);
+    created && !completed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn report() {
+        let tasks: Vec<Task> = [
+            "x 2023-12-06 2023-11-20 ship +work",
+            "x 2023-12-04 2023-11-27 test +work @office",
+            "2023-11-26 document +work @office",
+            "2023-12-01 call @phone",
+        ]
+        .iter()
+        .map(|line| Task::from_str(line).unwrap())
+        .collect();
+        let today = NaiveDate::from_ymd_opt(2023, 12, 6).unwrap();
+        let report = Report::new(&tasks, today, Weekday::Mon);
+
+        assert_eq!(report.completed_per_day[REPORT_DAYS - 1], 1);
+        assert_eq!(report.completed_per_day[REPORT_DAYS - 3], 1);
+        assert_eq!(
+            report.completed_per_week[REPORT_WEEKS - 1],
+            (NaiveDate::from_ymd_opt(2023, 12, 4).unwrap(), 2)
+        );
+        assert_eq!(report.average_open_age, Some(7.5));
+
+        // work: 2 open the week of 11-20, 3 the week of 11-27, 1 this week
+        let work = &report.burndowns[0];
+        assert_eq!(work.project, "work");
+        assert_eq!(&work.open[REPORT_WEEKS - 3..], &[2, 3, 1]);
+
+        assert_eq!(report.top_contexts[0], ("office".to_string(), 1));
+    }
+}
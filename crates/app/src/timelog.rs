@@ -0,0 +1,187 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+use std::collections::HashMap;
+use std::str::FromStr;
+use todo_txt::task::Task;
+
+/// Format of session start and end times in the time log
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Time spent on a task in one sitting, logged as one line of the time log,
+/// eg, `2023-11-30T09:00:00 2023-11-30T09:45:00 write spec +work`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    /// Task as it was at the end of the session
+    pub task: Task,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSessionError;
+
+impl Session {
+    pub fn duration(&self) -> chrono::Duration {
+        self.end - self.start
+    }
+}
+
+impl std::fmt::Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.start.format(TIME_FORMAT),
+            self.end.format(TIME_FORMAT),
+            self.task
+        )
+    }
+}
+
+impl FromStr for Session {
+    type Err = ParseSessionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.trim().splitn(3, ' ');
+        let mut time = || NaiveDateTime::parse_from_str(words.next()?, TIME_FORMAT).ok();
+        let (Some(start), Some(end)) = (time(), time()) else {
+            return Err(ParseSessionError);
+        };
+        let task = Task::from_str(words.next().ok_or(ParseSessionError)?)
+            .map_err(|_| ParseSessionError)?;
+        Ok(Session { start, end, task })
+    }
+}
+
+/// Read all sessions from a time log, skipping lines which are not sessions
+pub fn read_sessions<B: std::io::BufRead>(lines: &mut std::io::Lines<B>) -> Vec<Session> {
+    lines
+        .map_while(Result::ok)
+        .filter_map(|line| Session::from_str(&line).ok())
+        .collect()
+}
+
+/// Length of the period covered by a [TimeReport]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSpan {
+    Day,
+    Week,
+    Month,
+}
+
+impl TimeSpan {
+    /// First and last day of the period of this length containing `date`
+    pub fn range(&self, date: NaiveDate, week_start: Weekday) -> (NaiveDate, NaiveDate) {
+        match self {
+            TimeSpan::Day => (date, date),
+            TimeSpan::Week => {
+                let from = todo_txt::date::start_of_week(date, week_start);
+                (from, from + chrono::Duration::days(6))
+            }
+            TimeSpan::Month => {
+                let from = date.with_day(1).unwrap();
+                let to = (from + chrono::Months::new(1)).pred_opt().unwrap();
+                (from, to)
+            }
+        }
+    }
+
+    /// Date in the previous (`step` < 0) or following period to `date`
+    pub fn step(&self, date: NaiveDate, step: i32) -> NaiveDate {
+        match self {
+            TimeSpan::Day => date + chrono::Duration::days(step as i64),
+            TimeSpan::Week => date + chrono::Duration::weeks(step as i64),
+            TimeSpan::Month => {
+                let months = chrono::Months::new(step.unsigned_abs());
+                if step < 0 {
+                    date - months
+                } else {
+                    date + months
+                }
+            }
+        }
+    }
+}
+
+/// Time spent per project and context on sessions started within a range of
+/// days.  A session counts towards each of its task's projects and contexts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total: chrono::Duration,
+    /// Projects with time spent, most first
+    pub projects: Vec<(String, chrono::Duration)>,
+    /// Contexts with time spent, most first
+    pub contexts: Vec<(String, chrono::Duration)>,
+}
+
+impl TimeReport {
+    pub fn new(sessions: &[Session], from: NaiveDate, to: NaiveDate) -> TimeReport {
+        let sessions: Vec<&Session> = sessions
+            .iter()
+            .filter(|session| session.start.date() >= from && session.start.date() <= to)
+            .collect();
+
+        let total = sessions
+            .iter()
+            .map(|session| session.duration())
+            .fold(chrono::Duration::zero(), |a, b| a + b);
+        let by_name = |names: fn(&Task) -> &[String]| {
+            let mut spent: HashMap<String, chrono::Duration> = HashMap::new();
+            for session in &sessions {
+                for name in names(&session.task) {
+                    *spent
+                        .entry(name.clone())
+                        .or_insert_with(chrono::Duration::zero) += session.duration();
+                }
+            }
+            let mut spent: Vec<(String, chrono::Duration)> = spent.into_iter().collect();
+            spent.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            spent
+        };
+
+        TimeReport {
+            from,
+            to,
+            total,
+            projects: by_name(Task::projects),
+            contexts: by_name(Task::contexts),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_report() {
+        let log = "2023-11-30T09:00:00 2023-11-30T10:30:00 spec +work @office\n\
+                   not a session\n\
+                   2023-12-01T09:00:00 2023-12-01T09:30:00 spec +work\n\
+                   2023-12-04T20:00:00 2023-12-04T21:00:00 garden +home\n";
+        let sessions = read_sessions(&mut std::io::BufRead::lines(log.as_bytes()));
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].duration(), chrono::Duration::minutes(90));
+        assert_eq!(
+            sessions[1].to_string(),
+            "2023-12-01T09:00:00 2023-12-01T09:30:00 spec +work"
+        );
+
+        let date = NaiveDate::from_ymd_opt(2023, 11, 30).unwrap();
+        let (from, to) = TimeSpan::Week.range(date, Weekday::Mon);
+        let report = TimeReport::new(&sessions, from, to);
+        assert_eq!(report.total, chrono::Duration::minutes(120));
+        assert_eq!(
+            report.projects,
+            [("work".to_string(), chrono::Duration::minutes(120))]
+        );
+        assert_eq!(
+            report.contexts,
+            [("office".to_string(), chrono::Duration::minutes(90))]
+        );
+
+        let (from, to) = TimeSpan::Month.range(TimeSpan::Month.step(date, 1), Weekday::Mon);
+        assert_eq!(TimeReport::new(&sessions, from, to).projects.len(), 2);
+    }
+}
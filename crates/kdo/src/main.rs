@@ -1,5 +1,4 @@
 use clap::Parser;
-use std::io::BufRead;
 
 pub static ABOUT_STR: &str = r"
 A simple viewer/editor of TODO lists in the todo.txt format
@@ -9,36 +8,225 @@ A simple viewer/editor of TODO lists in the todo.txt format
 #[command(author, version)]
 #[command(about = ABOUT_STR, long_about = ui::terminal::KEYBIND_HELP_STR)]
 struct Args {
-    /// todo.txt file path. DEFAULT: $KDO_FILE_DEFAULT if set, else ./todo.txt
+    /// todo.txt file path. May be given multiple times to view several files
+    /// together. DEFAULT: $KDO_FILE_DEFAULT if set, else ./todo.txt
     #[arg(short, long)]
-    file: Option<String>,
+    file: Vec<String>,
+
+    /// First day of the week, eg, 'mon' or 'sunday'. Used for relative dates
+    /// such as 'due:eow', the date picker and weekly reports.
+    /// DEFAULT: $KDO_WEEK_START if set, else monday
+    #[arg(short, long)]
+    week_start: Option<String>,
+
+    /// File deleted tasks are appended to when saving.
+    /// DEFAULT: $KDO_TRASH_FILE if set, else deleted tasks are discarded
+    #[arg(short, long)]
+    trash: Option<String>,
+
+    /// Words added to quickly captured tasks, eg, '+inbox'. Tasks without
+    /// other contexts or projects are shown in the inbox view.
+    /// DEFAULT: $KDO_CAPTURE_DEFAULT if set, else none
+    #[arg(short, long)]
+    capture_default: Option<String>,
+
+    /// File timed sessions are appended to when saving, used for time reports.
+    /// DEFAULT: $KDO_TIME_LOG if set, else sessions are only reported until
+    /// kdo exits
+    #[arg(short = 'l', long)]
+    time_log: Option<String>,
+
+    /// Lengths of pomodoro work periods and breaks, eg, '50m/10m'.
+    /// DEFAULT: $KDO_POMODORO if set, else 25m/5m
+    #[arg(short, long)]
+    pomodoro: Option<String>,
+
+    /// Complete open subtasks when completing their parent.
+    /// DEFAULT: true if $KDO_COMPLETE_SUBTASKS is set (other than to 0)
+    #[arg(short = 's', long)]
+    complete_subtasks: bool,
+
+    /// Refuse to open files with tasks breaking the todo.txt format, such as a
+    /// priority on a completed task, instead of warning about them.
+    /// DEFAULT: true if $KDO_STRICT is set (other than to 0)
+    #[arg(long)]
+    strict: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Append a task to the (first) todo.txt file without starting the
+    /// interface
+    Capture {
+        /// Task text, eg, 'call bob due:fri'
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+}
+
+/// Append a captured task to the todo.txt file at `filename`, exiting on failure
+fn capture(filename: &str, text: &str, defaults: &str, week_start: chrono::Weekday) {
+    let today = chrono::Local::now().date_naive();
+    let Some(task) = app::capture::capture_task(text, defaults, today, week_start) else {
+        eprintln!("Nothing to add");
+        std::process::exit(1);
+    };
+
+    // Keep the file's line ending, starting on a new line if the file does not
+    // end with one
+    let (format, empty) = match std::fs::File::open(filename) {
+        Ok(file) => {
+            let empty = file.metadata().is_ok_and(|metadata| metadata.len() == 0);
+            let mut reader = todo_txt::file::TaskReader::new(std::io::BufReader::new(file));
+            if let Some(Err(err)) = reader.find(Result::is_err) {
+                eprintln!("Failed to read file '{}': {}", filename, err);
+                std::process::exit(1);
+            }
+            (reader.format(), empty)
+        }
+        Err(_) => (todo_txt::file::FileFormat::default(), true),
+    };
+    let line_ending = format.line_ending.as_str();
+    let mut line = String::new();
+    if !empty && !format.trailing_newline {
+        line.push_str(line_ending);
+    }
+    line.push_str(&task.to_string());
+    line.push_str(line_ending);
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filename);
+    let res = file.and_then(|mut file| {
+        use std::io::Write;
+        file.write_all(line.as_bytes())
+    });
+    match res {
+        Ok(()) => println!("Added: {}", task),
+        Err(err) => {
+            eprintln!("Failed to write file '{}': {}", filename, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Read all tasks from the todo.txt file at `filename` along with its format,
+/// exiting on failure.  Tasks breaking the specification are reported on
+/// stderr, and counted in the returned number of warnings.
+fn read_file(
+    filename: &str,
+    options: todo_txt::parse::ParseOptions,
+) -> (Vec<todo_txt::task::Task>, todo_txt::file::FileFormat, usize) {
+    let file = match std::fs::File::open(filename) {
+        Err(err) => {
+            eprintln!("Failed to open file '{}': {}", filename, err);
+            std::process::exit(1);
+        }
+        Ok(file) => file,
+    };
+
+    let mut reader =
+        todo_txt::file::TaskReader::with_options(std::io::BufReader::new(file), options);
+    let mut tasks = Vec::new();
+    for result in &mut reader {
+        match result {
+            Ok((_, task)) => tasks.push(task),
+            Err(err) => {
+                eprintln!("Failed to read file '{}': {}", filename, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for (line_no, violation) in reader.warnings() {
+        eprintln!("{}:{}: {}", filename, line_no, violation);
+    }
+    (tasks, reader.format(), reader.warnings().len())
 }
 
 fn main() {
     let args = Args::parse();
 
-    let filename = if let Some(file_arg) = args.file {
-        file_arg
+    let filenames = if !args.file.is_empty() {
+        args.file
     } else {
-        std::env::var("KDO_FILE_DEFAULT").unwrap_or("./todo.txt".to_string())
+        vec![std::env::var("KDO_FILE_DEFAULT").unwrap_or("./todo.txt".to_string())]
     };
-    // Create a path to the desired file
-    let file = match std::fs::File::open(&filename) {
-        Err(err) => {
-            println!("Failed to open file '{}': {}", filename, err);
-            std::process::exit(0);
+    let week_start = args
+        .week_start
+        .or(std::env::var("KDO_WEEK_START").ok())
+        .unwrap_or("mon".to_string());
+    let week_start = match week_start.parse::<chrono::Weekday>() {
+        Ok(week_start) => week_start,
+        Err(_) => {
+            eprintln!("Invalid week start '{}'", week_start);
+            std::process::exit(1);
         }
-        Ok(file) => file,
     };
+    let capture_default = args
+        .capture_default
+        .or(std::env::var("KDO_CAPTURE_DEFAULT").ok())
+        .unwrap_or_default();
+
+    if let Some(Command::Capture { text }) = args.command {
+        capture(&filenames[0], &text.join(" "), &capture_default, week_start);
+        return;
+    }
 
-    // Process tasks found in file
-    let reader = std::io::BufReader::new(file);
-    let tasks = todo_txt::read_tasks(&mut reader.lines());
-    let mut app = app::App::new("kdo v0.1", &filename, &tasks);
+    // Process tasks found in each file
+    let options = if args.strict || std::env::var("KDO_STRICT").is_ok_and(|value| value != "0") {
+        todo_txt::parse::ParseOptions::strict()
+    } else {
+        todo_txt::parse::ParseOptions::lenient()
+    };
+    let (tasks, format, mut warnings) = read_file(&filenames[0], options);
+    let mut app = app::App::new("kdo v0.1", &filenames[0], &tasks);
+    app.set_source_format(0, format);
+    for (source, filename) in filenames.iter().enumerate().skip(1) {
+        let (tasks, format, file_warnings) = read_file(filename, options);
+        app.add_source(filename, &tasks);
+        app.set_source_format(source, format);
+        warnings += file_warnings;
+    }
+    if warnings > 0 {
+        app.set_error(&format!(
+            "{} task(s) break the todo.txt format, see stderr",
+            warnings
+        ));
+    }
+    app.set_week_start(week_start);
+    app.set_capture_default(&capture_default);
+    app.set_complete_subtasks(
+        args.complete_subtasks
+            || std::env::var("KDO_COMPLETE_SUBTASKS").is_ok_and(|value| value != "0"),
+    );
+    let trash_file = args.trash.or(std::env::var("KDO_TRASH_FILE").ok());
+    if let Some(trash_file) = &trash_file {
+        app.set_trash_file(trash_file);
+    }
+    let time_log = args.time_log.or(std::env::var("KDO_TIME_LOG").ok());
+    if let Some(time_log) = &time_log {
+        app.set_time_log(time_log);
+    }
+    if let Some(pomodoro) = args.pomodoro.or(std::env::var("KDO_POMODORO").ok()) {
+        match app::pomodoro::parse_lengths(&pomodoro) {
+            Some((work_length, break_length)) => {
+                app.set_pomodoro_lengths(work_length, break_length)
+            }
+            None => {
+                eprintln!("Invalid pomodoro lengths '{}'", pomodoro);
+                std::process::exit(1);
+            }
+        }
+    }
     let mut ui_state = ui::state::State::new();
 
     let res = ui::terminal::run(&mut app, &mut ui_state);
     if let Err(err) = res {
-        println!("{:?}", err);
+        eprintln!("{:?}", err);
+        std::process::exit(1);
     }
 }
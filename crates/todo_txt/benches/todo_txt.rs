@@ -0,0 +1,90 @@
+use std::io::BufRead;
+
+use todo_txt::testing::todo_files;
+
+fn read(text: &str) -> Vec<todo_txt::task::Task> {
+    todo_txt::read_tasks(&mut text.as_bytes().lines())
+}
+
+fn parse_line(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("parse_line");
+    for (name, line) in [
+        ("simple", "call mom"),
+        (
+            "open",
+            "(A) 2023-11-01 write spec @work.office +work.backend due:2023-12-01 id:7",
+        ),
+        (
+            "completed",
+            "x 2023-11-30 2023-11-01 write spec @work +work.backend",
+        ),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| todo_txt::parse::TaskParser::new().parse_line(criterion::black_box(line)))
+        });
+        group.bench_function(format!("borrowed/{}", name), |b| {
+            b.iter(|| todo_txt::parse::TaskRef::parse(criterion::black_box(line)))
+        });
+    }
+    group.finish();
+}
+
+fn read_tasks(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("read_tasks");
+    group.sample_size(10);
+    for (name, text) in todo_files() {
+        group.bench_function(&name, |b| b.iter(|| read(&text)));
+
+        // Scanning the file with borrowed tasks, without allocating
+        group.bench_function(format!("borrowed/{}", name), |b| {
+            b.iter(|| {
+                text.lines()
+                    .filter_map(todo_txt::parse::TaskRef::parse)
+                    .filter(|task| task.contexts().any(|(_, context)| context == "work"))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn filter(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("filter");
+    for (name, text) in todo_files() {
+        let tasks = read(&text);
+
+        // The chain applied by the task pane for a context and priority
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                todo_txt::tasks::TasksFilter::new(&tasks)
+                    .without_completed(true)
+                    .with_project(None)
+                    .with_context(Some("work"))
+                    .with_priority(Some('A'))
+                    .with_ancestors(false)
+                    .task_indices
+            })
+        });
+    }
+    group.finish();
+}
+
+fn collect(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("collect");
+    for (name, text) in todo_files() {
+        let tasks = read(&text);
+        group.bench_function(format!("contexts/{}", name), |b| {
+            b.iter(|| todo_txt::tasks::collect_contexts(&tasks, true))
+        });
+        group.bench_function(format!("projects/{}", name), |b| {
+            b.iter(|| todo_txt::tasks::collect_projects(&tasks, true))
+        });
+        group.bench_function(format!("priorities/{}", name), |b| {
+            b.iter(|| todo_txt::tasks::collect_priorities(&tasks, true))
+        });
+    }
+    group.finish();
+}
+
+criterion::criterion_group!(benches, parse_line, read_tasks, filter, collect);
+criterion::criterion_main!(benches);
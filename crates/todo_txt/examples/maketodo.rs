@@ -0,0 +1,17 @@
+//! Generate a todo.txt file of random tasks for benchmarking, eg,
+//! ```text
+//! cargo run --release -p todo_txt --features testing --example maketodo -- 100000 > data/huge_todo.txt
+//! ```
+//! Like `data/maketodo.py`, but without dependencies and fast enough for files
+//! of hundreds of thousands of tasks.
+
+/// Number of tasks generated if not given on the command line
+const DEFAULT_LINES: usize = 100_000;
+
+fn main() {
+    let lines = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("number of tasks"))
+        .unwrap_or(DEFAULT_LINES);
+    print!("{}", todo_txt::testing::generate(lines, 1));
+}
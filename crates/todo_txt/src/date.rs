@@ -0,0 +1,233 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+
+/// Tag keys whose values are dates, eg, `due:2023-12-01` and the threshold
+/// date `t:2023-11-20`
+pub const DATE_TAG_KEYS: [&str; 2] = ["due", "t"];
+
+/// First day of the week containing `date`, for weeks starting on `week_start`
+pub fn start_of_week(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let days = (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    date - chrono::Duration::days(days as i64)
+}
+
+/// Parse an ISO date or a date relative to `today`.  Accepted forms are:
+///
+/// - `2023-12-01`: ISO date
+/// - `today`, `tomorrow`/`tom`, `yesterday`
+/// - `mon`..`sun` or `monday`..`sunday`: the next such day after today
+/// - `+3d`, `2w`, `-1m`, `+1y`: offset in days, weeks, months or years
+/// - `eow`, `eom`, `eoy`: last day of this week, month or year
+///
+/// Words are matched ignoring case.  The end of week depends on `week_start`.
+pub fn parse_date(s: &str, today: NaiveDate, week_start: Weekday) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let s = s.to_lowercase();
+    match s.as_str() {
+        "today" => return Some(today),
+        "tomorrow" | "tom" => return today.succ_opt(),
+        "yesterday" => return today.pred_opt(),
+        "eow" => return Some(start_of_week(today, week_start) + chrono::Duration::days(6)),
+        "eom" => {
+            let next_month = today.with_day(1)? + chrono::Months::new(1);
+            return next_month.pred_opt();
+        }
+        "eoy" => return NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        _ => {}
+    }
+
+    if let Ok(weekday) = s.parse::<Weekday>() {
+        let days =
+            (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        let days = if days == 0 { 7 } else { days };
+        return Some(today + chrono::Duration::days(days as i64));
+    }
+
+    parse_offset(&s, today)
+}
+
+/// Parse an offset such as `+3d` or `-2w` relative to `today`
+fn parse_offset(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (negative, s) = match s.as_bytes().first()? {
+        b'+' => (false, &s[1..]),
+        b'-' => (true, &s[1..]),
+        _ => (false, s),
+    };
+    let unit = s.chars().last()?;
+    let count: u32 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+
+    match unit {
+        'd' | 'w' => {
+            let days = if unit == 'w' {
+                count as i64 * 7
+            } else {
+                count as i64
+            };
+            let duration = chrono::Duration::days(days);
+            if negative {
+                today.checked_sub_signed(duration)
+            } else {
+                today.checked_add_signed(duration)
+            }
+        }
+        'm' | 'y' => {
+            let months = if unit == 'y' {
+                count.checked_mul(12)?
+            } else {
+                count
+            };
+            let months = chrono::Months::new(months);
+            if negative {
+                today.checked_sub_months(months)
+            } else {
+                today.checked_add_months(months)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Replace relative dates in the values of [DATE_TAG_KEYS] tags of a task line
+/// with ISO dates, eg, `due:tomorrow` becomes `due:2023-12-02`.  Values which
+/// can not be parsed are left as they are.
+pub fn normalize_dates(line: &str, today: NaiveDate, week_start: Weekday) -> String {
+    line.split_whitespace()
+        .map(|word| match crate::parse::split_tag(word) {
+            Some((key, value)) if DATE_TAG_KEYS.contains(&key) => {
+                match parse_date(value, today, week_start) {
+                    Some(date) => format!("{}:{}", key, date),
+                    None => word.to_string(),
+                }
+            }
+            _ => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse a duration such as `1h30m`, `45m` or `2h`, made of hours, minutes and
+/// seconds in that order.  Durations too long to represent are rejected.
+pub fn parse_duration(s: &str) -> Option<chrono::Duration> {
+    let mut total = chrono::Duration::zero();
+    let mut rest = s;
+    let mut units = "hms".chars();
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let count: i64 = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        // Each unit may be given once, after any larger units
+        units.by_ref().find(|u| *u == unit)?;
+        let duration = match unit {
+            'h' => chrono::Duration::try_hours(count),
+            'm' => chrono::Duration::try_minutes(count),
+            _ => chrono::Duration::try_seconds(count),
+        };
+        total = total.checked_add(&duration?)?;
+        rest = &rest[digits + 1..];
+    }
+    (!s.is_empty()).then_some(total)
+}
+
+/// Format a duration in hours and minutes, rounded to the nearest minute, eg,
+/// `1h30m`, for display.  See [parse_duration].
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let minutes = (duration.num_seconds().max(0) + 30) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h{}m", hours, minutes),
+    }
+}
+
+/// Format a duration exactly in hours, minutes and seconds, leaving out zero
+/// units, eg, `1h30s`, so that adding up durations does not lose time.  See
+/// [parse_duration].
+pub fn format_exact_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let units = [
+        (seconds / 3600, 'h'),
+        (seconds / 60 % 60, 'm'),
+        (seconds % 60, 's'),
+    ];
+    let formatted: String = units
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, unit)| format!("{}{}", count, unit))
+        .collect();
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn relative_dates() {
+        // A thursday
+        let today = date(2023, 11, 30);
+        let parse = |s| parse_date(s, today, Weekday::Mon);
+
+        assert_eq!(parse("2024-01-02"), Some(date(2024, 1, 2)));
+        assert_eq!(parse("Tomorrow"), Some(date(2023, 12, 1)));
+        assert_eq!(parse("fri"), Some(date(2023, 12, 1)));
+        assert_eq!(parse("thursday"), Some(date(2023, 12, 7)));
+        assert_eq!(parse("+2w"), Some(date(2023, 12, 14)));
+        assert_eq!(parse("-3d"), Some(date(2023, 11, 27)));
+        assert_eq!(parse("1m"), Some(date(2023, 12, 30)));
+        assert_eq!(parse("eom"), Some(date(2023, 11, 30)));
+        assert_eq!(parse("eoy"), Some(date(2023, 12, 31)));
+        assert_eq!(parse("eow"), Some(date(2023, 12, 3)));
+        assert_eq!(
+            parse_date("eow", today, Weekday::Sun),
+            Some(date(2023, 12, 2))
+        );
+        assert_eq!(parse("soon"), None);
+        assert_eq!(parse("+w"), None);
+        assert_eq!(parse("+400000000y"), None);
+        assert_eq!(parse("-4000000000d"), None);
+
+        assert_eq!(
+            normalize_dates("call bob  due:fri t:+1d rec:+1w", today, Weekday::Mon),
+            "call bob due:2023-12-01 t:2023-12-01 rec:+1w"
+        );
+    }
+
+    #[test]
+    fn durations() {
+        let minutes = chrono::Duration::minutes;
+        assert_eq!(parse_duration("1h30m"), Some(minutes(90)));
+        assert_eq!(parse_duration("45m"), Some(minutes(45)));
+        assert_eq!(
+            parse_duration("2h10s"),
+            Some(minutes(120) + chrono::Duration::seconds(10))
+        );
+        assert_eq!(parse_duration("30m1h"), None);
+        assert_eq!(parse_duration("1x"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("9999999999999h"), None);
+        assert_eq!(parse_duration("2562047788015h59m"), None);
+
+        assert_eq!(format_duration(minutes(90)), "1h30m");
+        assert_eq!(
+            format_duration(minutes(120) + chrono::Duration::seconds(40)),
+            "2h1m"
+        );
+        assert_eq!(format_duration(chrono::Duration::seconds(20)), "0m");
+
+        let exact = minutes(120) + chrono::Duration::seconds(40);
+        assert_eq!(format_exact_duration(exact), "2h40s");
+        assert_eq!(parse_duration(&format_exact_duration(exact)), Some(exact));
+        assert_eq!(format_exact_duration(minutes(90)), "1h30m");
+        assert_eq!(format_exact_duration(chrono::Duration::zero()), "0s");
+    }
+}
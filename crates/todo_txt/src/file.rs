@@ -0,0 +1,298 @@
+use crate::parse::{ParseOptions, ParseTaskError, SpecViolation, TaskRef};
+use crate::task::Task;
+
+//------------------------------------------------------------------------------
+//
+// File format
+//
+//------------------------------------------------------------------------------
+
+/// Byte order mark some editors write at the start of UTF-8 files
+const BOM: &str = "\u{feff}";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Details of how a todo.txt file was written, so it can be written back the
+/// same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFormat {
+    pub line_ending: LineEnding,
+    /// The file starts with a byte order mark
+    pub bom: bool,
+    /// The last line ends with a line ending
+    pub trailing_newline: bool,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        FileFormat {
+            line_ending: LineEnding::Lf,
+            bom: false,
+            trailing_newline: true,
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+//
+// Reading
+//
+//------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum ReadTaskError {
+    Io(std::io::Error),
+    /// The line with the given number is not a task.  Only returned when
+    /// reading with [ParseOptions::strict], as lenient parsing accepts any line
+    Parse(usize, ParseTaskError),
+}
+
+impl std::fmt::Display for ReadTaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReadTaskError::Io(err) => write!(f, "{}", err),
+            ReadTaskError::Parse(line_no, err) => write!(f, "line {}: {}", line_no, err),
+        }
+    }
+}
+
+impl std::error::Error for ReadTaskError {}
+
+impl From<std::io::Error> for ReadTaskError {
+    fn from(err: std::io::Error) -> Self {
+        ReadTaskError::Io(err)
+    }
+}
+
+/// Reads tasks one line at a time, yielding each with its (1-based) line
+/// number and skipping blank lines.  The [FileFormat] seen so far is available
+/// from [TaskReader::format], eg, to write the tasks back with [TaskWriter],
+/// and the rules of the specification broken by leniently parsed tasks from
+/// [TaskReader::warnings].
+/// ```no_run
+/// # let file = std::fs::File::open("todo.txt").unwrap();
+/// let mut reader = todo_txt::file::TaskReader::new(std::io::BufReader::new(file));
+/// for result in &mut reader {
+///     let (line_no, task) = result.unwrap();
+///     println!("{}: {}", line_no, task);
+/// }
+/// let format = reader.format();
+/// ```
+pub struct TaskReader<R: std::io::BufRead> {
+    reader: R,
+    line: String,
+    line_no: usize,
+    format: FileFormat,
+    options: ParseOptions,
+    warnings: Vec<(usize, SpecViolation)>,
+    done: bool,
+}
+
+impl<R: std::io::BufRead> TaskReader<R> {
+    pub fn new(reader: R) -> Self {
+        TaskReader::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        TaskReader {
+            reader,
+            line: String::new(),
+            line_no: 0,
+            format: FileFormat::default(),
+            options,
+            warnings: Vec::new(),
+            done: false,
+        }
+    }
+
+    pub fn format(&self) -> FileFormat {
+        self.format
+    }
+
+    /// Line numbers of the tasks read so far which break the specification,
+    /// with the rules broken
+    pub fn warnings(&self) -> &[(usize, SpecViolation)] {
+        &self.warnings
+    }
+
+    /// Read the next line into `line` without its line ending, returning false
+    /// at the end of the input
+    fn read_line(&mut self) -> std::io::Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_no += 1;
+
+        if self.line_no == 1 {
+            // The line ending and BOM of the first line are taken to be those
+            // of the whole file
+            if self.line.ends_with("\r\n") {
+                self.format.line_ending = LineEnding::CrLf;
+            }
+            if self.line.starts_with(BOM) {
+                self.line.drain(..BOM.len());
+                self.format.bom = true;
+            }
+        }
+        self.format.trailing_newline = self.line.ends_with('\n');
+        let len = self.line.trim_end_matches(['\r', '\n']).len();
+        self.line.truncate(len);
+        Ok(true)
+    }
+}
+
+impl<R: std::io::BufRead> Iterator for TaskReader<R> {
+    type Item = Result<(usize, Task), ReadTaskError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.read_line() {
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+                Ok(false) => self.done = true,
+                Ok(true) if self.line.trim().is_empty() => {}
+                Ok(true) => {
+                    return Some(match TaskRef::parse_with(&self.line, &self.options) {
+                        Ok(task) => {
+                            let line_no = self.line_no;
+                            self.warnings
+                                .extend(task.violations().map(|violation| (line_no, violation)));
+                            Ok((line_no, task.to_task()))
+                        }
+                        Err(err) => Err(ReadTaskError::Parse(self.line_no, err)),
+                    })
+                }
+            }
+        }
+        None
+    }
+}
+
+//------------------------------------------------------------------------------
+//
+// Writing
+//
+//------------------------------------------------------------------------------
+
+/// Writes tasks one per line in the given [FileFormat].  [TaskWriter::finish]
+/// must be called once all tasks are written to end the last line if needed.
+pub struct TaskWriter<W: std::io::Write> {
+    writer: W,
+    format: FileFormat,
+    started: bool,
+}
+
+impl<W: std::io::Write> TaskWriter<W> {
+    pub fn new(writer: W, format: FileFormat) -> Self {
+        TaskWriter {
+            writer,
+            format,
+            started: false,
+        }
+    }
+
+    fn start(&mut self) -> std::io::Result<()> {
+        if !self.started && self.format.bom {
+            self.writer.write_all(BOM.as_bytes())?;
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    pub fn write_task(&mut self, task: &Task) -> std::io::Result<()> {
+        if self.started {
+            self.writer
+                .write_all(self.format.line_ending.as_str().as_bytes())?;
+        }
+        self.start()?;
+        write!(self.writer, "{}", task)
+    }
+
+    /// End the last line according to the format and flush, returning the
+    /// underlying writer
+    pub fn finish(mut self) -> std::io::Result<W> {
+        let wrote_tasks = self.started;
+        self.start()?;
+        if wrote_tasks && self.format.trailing_newline {
+            self.writer
+                .write_all(self.format.line_ending.as_str().as_bytes())?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "\u{feff}(A) call mom\r\n\r\nx 2023-11-30 pay rent +home";
+        let mut reader = TaskReader::new(text.as_bytes());
+        let tasks: Vec<(usize, Task)> = (&mut reader).map(Result::unwrap).collect();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].0, 3);
+        assert_eq!(tasks[1].1.projects(), ["home"]);
+        let format = reader.format();
+        assert_eq!(
+            format,
+            FileFormat {
+                line_ending: LineEnding::CrLf,
+                bom: true,
+                trailing_newline: false,
+            }
+        );
+
+        let mut writer = TaskWriter::new(Vec::new(), format);
+        for (_, task) in &tasks {
+            writer.write_task(task).unwrap();
+        }
+        let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(
+            written,
+            "\u{feff}(A) call mom\r\nx 2023-11-30 pay rent +home"
+        );
+
+        // Tasks breaking the specification are reported, or rejected if strict
+        let text = "x (A) call mom\n2023-11-30 2023-11-01 pay rent\n";
+        let mut reader = TaskReader::new(text.as_bytes());
+        assert_eq!((&mut reader).filter(Result::is_ok).count(), 2);
+        assert_eq!(
+            reader.warnings(),
+            [
+                (1, SpecViolation::PriorityOnCompletedTask),
+                (2, SpecViolation::CompletionDateOnOpenTask)
+            ]
+        );
+        let mut reader = TaskReader::with_options(text.as_bytes(), ParseOptions::strict());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(ReadTaskError::Parse(
+                1,
+                ParseTaskError::Violation(SpecViolation::PriorityOnCompletedTask)
+            )))
+        ));
+
+        let mut writer = TaskWriter::new(Vec::new(), FileFormat::default());
+        writer.write_task(&tasks[0].1).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"(A) call mom\n");
+    }
+}
@@ -1,14 +1,21 @@
+pub mod date;
+pub mod file;
 pub mod parse;
 pub mod task;
 pub mod tasks;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 use std::str::FromStr;
 
 /// Read tasks from a line buffer.  Can be used to read from file as such:
-/// ```
+/// ```no_run
+/// # use std::io::BufRead;
+/// # let file = std::fs::File::open("todo.txt").unwrap();
 /// let reader = std::io::BufReader::new(file);
 /// let tasks = todo_txt::read_tasks(&mut reader.lines());
 /// ```
+/// See [file::TaskReader] to handle IO errors and keep the file's format.
 pub fn read_tasks<B: std::io::BufRead>(lines: &mut std::io::Lines<B>) -> Vec<task::Task> {
     let mut tasks = Vec::new();
     for line in lines.map(|line| line.unwrap()) {
@@ -4,13 +4,93 @@ use regex::Regex;
 
 use crate::task::Task;
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ParseTaskError;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseTaskError {
+    /// The line is blank
+    Empty,
+    /// The line breaks a rule of the todo.txt format, in [ParseMode::Strict]
+    Violation(SpecViolation),
+}
+
+impl std::fmt::Display for ParseTaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseTaskError::Empty => write!(f, "empty line"),
+            ParseTaskError::Violation(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+/// A rule of the todo.txt format specification broken by a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecViolation {
+    /// Completed tasks have no priority, eg, `x (A) call mom`
+    PriorityOnCompletedTask,
+    /// Only completed tasks have a completion date, eg, `2023-11-30 2023-11-01 call mom`
+    CompletionDateOnOpenTask,
+    /// The task was completed before it was created
+    CompletedBeforeCreated,
+}
+
+impl std::fmt::Display for SpecViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let msg = match self {
+            SpecViolation::PriorityOnCompletedTask => "priority on a completed task",
+            SpecViolation::CompletionDateOnOpenTask => "completion date on an open task",
+            SpecViolation::CompletedBeforeCreated => "completion date before creation date",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// How closely lines are held to the todo.txt format specification.  Both
+/// modes read a line the same way, eg, a single date after `x` is the creation
+/// date, and only differ in whether tasks breaking the specification are
+/// accepted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Accept tasks breaking the specification, reporting the rules broken as
+    /// warnings
+    #[default]
+    Lenient,
+    /// Reject tasks breaking the specification
+    Strict,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+}
+
+impl ParseOptions {
+    pub fn lenient() -> Self {
+        ParseOptions {
+            mode: ParseMode::Lenient,
+        }
+    }
+
+    pub fn strict() -> Self {
+        ParseOptions {
+            mode: ParseMode::Strict,
+        }
+    }
+}
 
 lazy_static! {
     static ref RE_PRIORITY: Regex = Regex::new(r"^\(([A-Z])\)$").unwrap();
 }
 
+/// Split a `key:value` tag into its key and value.  Neither may be empty or
+/// contain a colon, so eg, URLs are not mistaken for tags.
+pub fn split_tag(token: &str) -> Option<(&str, &str)> {
+    let (key, value) = token.split_once(':')?;
+    if key.is_empty() || value.is_empty() || value.contains(':') {
+        None
+    } else {
+        Some((key, value))
+    }
+}
+
 //------------------------------------------------------------------------------
 //
 // Parsing state machine
@@ -60,90 +140,469 @@ enum ParseState {
     InDescription,
 }
 
+fn next_state(state: &ParseState, token: Token) -> ParseState {
+    match (state, token) {
+        // Start state explicit transitions
+        (ParseState::Start, Token::Complete) => ParseState::PastCompletion,
+        (ParseState::Start, Token::Priority(_)) => ParseState::PastPriority,
+        (ParseState::Start, Token::Date(_)) => ParseState::PastDate1,
+
+        // PastCompletion explicit transitions
+        (ParseState::PastCompletion, Token::Priority(_)) => ParseState::PastPriority,
+        (ParseState::PastCompletion, Token::Date(_)) => ParseState::PastDate1,
+
+        // PastPriority explicit transitions
+        (ParseState::PastPriority, Token::Date(_)) => ParseState::PastDate1,
+
+        // PastDate1 explicit transitions
+        (ParseState::PastDate1, Token::Date(_)) => ParseState::PastDate2,
+        (_, _) => ParseState::InDescription,
+    }
+}
+
+//------------------------------------------------------------------------------
+//
+// Escaping
+//
+//------------------------------------------------------------------------------
+
+/// Prefix written before a description whose first word would otherwise be
+/// read back as the completion mark, priority or a date, eg, the description
+/// `2023-11-30 was a good day` of a task without dates is written as
+/// `\2023-11-30 was a good day`
+pub const ESCAPE: char = '\\';
+
+/// Whether the word, starting a description after the given tokens, would be
+/// read as part of the task rather than of its description.  Escaped words
+/// are checked without their escapes, so escapes are themselves escaped.
+fn is_ambiguous(state: &ParseState, word: &str) -> bool {
+    let word = word.trim_start_matches(ESCAPE);
+    next_state(state, Token::lex(word)) != ParseState::InDescription
+}
+
+/// Whether the description of `task` must be written escaped, see [ESCAPE]
+pub fn needs_escape(task: &Task, description: &str) -> bool {
+    let Some(word) = description.split_whitespace().next() else {
+        return false;
+    };
+    let tokens = [
+        task.completed.then_some(Token::Complete),
+        task.priority.map(Token::Priority),
+        task.date_completed
+            .filter(|_| task.date_created.is_some())
+            .map(Token::Date),
+        task.date_created.map(Token::Date),
+    ];
+    let state = tokens
+        .into_iter()
+        .flatten()
+        .fold(ParseState::Start, |state, token| next_state(&state, token));
+    is_ambiguous(&state, word)
+}
+
+/// Parses lines into owned [Task]s, see [TaskRef] for a borrowed view
 pub struct TaskParser {
-    state: ParseState,
-    task: Task,
+    options: ParseOptions,
 }
 
 impl TaskParser {
     pub fn new() -> Self {
-        TaskParser {
-            state: ParseState::Start,
-            task: Default::default(),
-        }
+        TaskParser::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        TaskParser { options }
+    }
+
+    pub fn parse_line(self, line: &str) -> Option<Task> {
+        self.parse(line).ok().map(|(task, _)| task)
+    }
+
+    /// Parse a line along with the rules of the specification it breaks, which
+    /// are always empty in [ParseMode::Strict]
+    pub fn parse(&self, line: &str) -> Result<(Task, Vec<SpecViolation>), ParseTaskError> {
+        let task = TaskRef::parse_with(line, &self.options)?;
+        Ok((task.to_task(), task.violations().collect()))
+    }
+}
+
+impl Default for TaskParser {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn parse_line(mut self, line: &str) -> Option<Task> {
-        let mut token_iter = line.split_whitespace();
-
-        while let Some(token) = token_iter.next() {
-            self.state = self.next(Token::lex(token));
-            if self.state == ParseState::InDescription {
-                self.task.set_description(
-                    format!(
-                        "{} {}",
-                        token,
-                        //token_iter.remainder().unwrap_or("") // Not in stable yet :(
-                        token_iter.collect::<Vec<_>>().join(" ")
-                    )
-                    .trim(),
-                );
+//------------------------------------------------------------------------------
+//
+// Borrowed tasks
+//
+//------------------------------------------------------------------------------
+
+/// Whitespace separated words of `line`, with their byte ranges
+fn words(line: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    line.split_whitespace().map(move |word| {
+        let start = word.as_ptr() as usize - line.as_ptr() as usize;
+        (start..start + word.len(), word)
+    })
+}
+
+/// A task borrowed from the line it was parsed from, so large files can be
+/// scanned without allocating.  Each part of the task is available both as a
+/// value and as a byte range (span) into the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskRef<'a> {
+    pub completed: bool,
+    pub priority: Option<char>,
+    pub date_completed: Option<chrono::NaiveDate>,
+    pub date_created: Option<chrono::NaiveDate>,
+
+    line: &'a str,
+    priority_span: Option<std::ops::Range<usize>>,
+    date_completed_span: Option<std::ops::Range<usize>>,
+    date_created_span: Option<std::ops::Range<usize>>,
+    description_span: std::ops::Range<usize>,
+}
+
+impl<'a> TaskRef<'a> {
+    /// Parse a line leniently, returning `None` if it is blank
+    pub fn parse(line: &'a str) -> Option<TaskRef<'a>> {
+        TaskRef::parse_with(line, &ParseOptions::lenient()).ok()
+    }
+
+    pub fn parse_with(
+        line: &'a str,
+        options: &ParseOptions,
+    ) -> Result<TaskRef<'a>, ParseTaskError> {
+        let end = line.trim_end().len();
+        let mut task = TaskRef {
+            completed: false,
+            priority: None,
+            date_completed: None,
+            date_created: None,
+            line,
+            priority_span: None,
+            date_completed_span: None,
+            date_created_span: None,
+            description_span: end..end,
+        };
+
+        let mut state = ParseState::Start;
+        for (span, word) in words(line) {
+            let next = task.next(&state, Token::lex(word), span.clone());
+            if next == ParseState::InDescription {
+                // Drop the escape of a word which would be read as metadata
+                let escaped = word
+                    .strip_prefix(ESCAPE)
+                    .is_some_and(|word| is_ambiguous(&state, word));
+                task.description_span = span.start + escaped as usize..end;
+                state = next;
                 break;
             }
+            state = next;
         }
 
-        if self.state == ParseState::Start {
-            None
-        } else {
-            Some(self.task)
+        if state == ParseState::Start {
+            return Err(ParseTaskError::Empty);
         }
+        if options.mode == ParseMode::Strict {
+            if let Some(violation) = task.violations().next() {
+                return Err(ParseTaskError::Violation(violation));
+            }
+        }
+        Ok(task)
+    }
+
+    /// Rules of the specification the task breaks
+    pub fn violations(&self) -> impl Iterator<Item = SpecViolation> {
+        let completed_before_created = matches!(
+            (self.date_completed, self.date_created),
+            (Some(completed), Some(created)) if completed < created
+        );
+        [
+            (self.completed && self.priority.is_some())
+                .then_some(SpecViolation::PriorityOnCompletedTask),
+            (!self.completed && self.date_completed.is_some())
+                .then_some(SpecViolation::CompletionDateOnOpenTask),
+            completed_before_created.then_some(SpecViolation::CompletedBeforeCreated),
+        ]
+        .into_iter()
+        .flatten()
     }
 
-    fn next(&mut self, token: Token) -> ParseState {
-        match (&self.state, token) {
-            // Start state explicit transitions
-            (ParseState::Start, Token::Complete) => {
-                self.task.completed = true;
-                ParseState::PastCompletion
+    fn next(
+        &mut self,
+        state: &ParseState,
+        token: Token,
+        span: std::ops::Range<usize>,
+    ) -> ParseState {
+        let next = next_state(state, token);
+        match (&next, token) {
+            (ParseState::PastCompletion, Token::Complete) => self.completed = true,
+            (ParseState::PastPriority, Token::Priority(p)) => {
+                self.priority = Some(p);
+                self.priority_span = Some(span);
             }
-            (ParseState::Start, Token::Priority(p)) => {
-                self.task.priority = Some(p);
-                ParseState::PastPriority
+            (ParseState::PastDate1, Token::Date(date)) => {
+                self.date_created = Some(date);
+                self.date_created_span = Some(span);
             }
-            (ParseState::Start, Token::Date(date)) => {
-                self.task.date_created = Some(date);
-                ParseState::PastDate1
+            (ParseState::PastDate2, Token::Date(date)) => {
+                self.date_completed = self.date_created;
+                self.date_completed_span = self.date_created_span.take();
+                self.date_created = Some(date);
+                self.date_created_span = Some(span);
             }
+            (_, _) => {}
+        }
+        next
+    }
 
-            // PastCompletion explicit transitions
-            (ParseState::PastCompletion, Token::Priority(p)) => {
-                self.task.priority = Some(p);
-                ParseState::PastPriority
-            }
-            (ParseState::PastCompletion, Token::Date(date)) => {
-                self.task.date_created = Some(date);
-                ParseState::PastDate1
-            }
+    /// The line the task was parsed from
+    pub fn line(&self) -> &'a str {
+        self.line
+    }
 
-            // PastPriority explicit transitions
-            (ParseState::PastPriority, Token::Date(date)) => {
-                self.task.date_created = Some(date);
-                ParseState::PastDate1
-            }
+    /// The rest of the line after the completion mark, priority and dates, and
+    /// any [ESCAPE] of its first word.  Unlike [Task::description], runs of
+    /// whitespace are kept as they are.
+    pub fn description(&self) -> &'a str {
+        &self.line[self.description_span.clone()]
+    }
 
-            // PastDate1 explicit transitions
-            (ParseState::PastDate1, Token::Date(date)) => {
-                self.task.date_completed = self.task.date_created;
-                self.task.date_created = Some(date);
-                ParseState::PastDate2
+    pub fn priority_span(&self) -> Option<std::ops::Range<usize>> {
+        self.priority_span.clone()
+    }
+
+    pub fn date_completed_span(&self) -> Option<std::ops::Range<usize>> {
+        self.date_completed_span.clone()
+    }
+
+    pub fn date_created_span(&self) -> Option<std::ops::Range<usize>> {
+        self.date_created_span.clone()
+    }
+
+    pub fn description_span(&self) -> std::ops::Range<usize> {
+        self.description_span.clone()
+    }
+
+    /// Words of the description with their spans in the line
+    fn description_words(&self) -> impl Iterator<Item = (std::ops::Range<usize>, &'a str)> {
+        let start = self.description_span.start;
+        words(self.description())
+            .map(move |(span, word)| (start + span.start..start + span.end, word))
+    }
+
+    /// Contexts (without the `@`) with the spans of their words
+    pub fn contexts(&self) -> impl Iterator<Item = (std::ops::Range<usize>, &'a str)> {
+        self.description_words()
+            .filter_map(|(span, word)| Some((span, word.strip_prefix('@')?)))
+    }
+
+    /// Projects (without the `+`) with the spans of their words
+    pub fn projects(&self) -> impl Iterator<Item = (std::ops::Range<usize>, &'a str)> {
+        self.description_words()
+            .filter_map(|(span, word)| Some((span, word.strip_prefix('+')?)))
+    }
+
+    /// `key:value` tags with the spans of their words, in order of appearance
+    pub fn tags(&self) -> impl Iterator<Item = (std::ops::Range<usize>, (&'a str, &'a str))> {
+        self.description_words()
+            .filter(|(_, word)| !word.starts_with(['@', '+']))
+            .filter_map(|(span, word)| Some((span, split_tag(word)?)))
+    }
+
+    /// Value of the first tag with the given key
+    pub fn tag(&self, key: &str) -> Option<&'a str> {
+        self.tags()
+            .find(|(_, (k, _))| *k == key)
+            .map(|(_, (_, value))| value)
+    }
+
+    /// Owned copy of the task, with the words of its description separated by
+    /// single spaces
+    pub fn to_task(&self) -> Task {
+        let mut description = String::with_capacity(self.description_span.len());
+        for (_, word) in words(self.description()) {
+            if !description.is_empty() {
+                description.push(' ');
             }
-            (_, _) => ParseState::InDescription,
+            description.push_str(word);
         }
+
+        let mut task = Task::default();
+        task.completed = self.completed;
+        task.priority = self.priority;
+        task.date_completed = self.date_completed;
+        task.date_created = self.date_created;
+        task.set_description(&description);
+        task
     }
 }
 
-impl Default for TaskParser {
-    fn default() -> Self {
-        Self::new()
+impl<'a> From<TaskRef<'a>> for Task {
+    fn from(task: TaskRef<'a>) -> Task {
+        task.to_task()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn task_ref() {
+        let line = "x 2023-11-30 2023-11-01 write  spec @work +docs due:2023-12-01 ";
+        let task = TaskRef::parse(line).unwrap();
+        assert!(task.completed);
+        assert_eq!(&line[task.date_completed_span().unwrap()], "2023-11-30");
+        assert_eq!(&line[task.date_created_span().unwrap()], "2023-11-01");
+        assert_eq!(task.description(), "write  spec @work +docs due:2023-12-01");
+
+        let (span, context) = task.contexts().next().unwrap();
+        assert_eq!((&line[span], context), ("@work", "work"));
+        assert_eq!(
+            task.projects().map(|(_, p)| p).collect::<Vec<_>>(),
+            ["docs"]
+        );
+        assert_eq!(task.tag("due"), Some("2023-12-01"));
+
+        // The owned task has the same parts, with whitespace runs collapsed
+        let owned = task.to_task();
+        assert_eq!(
+            owned.to_string(),
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+        assert_eq!(owned.contexts(), ["work"]);
+
+        let task = TaskRef::parse("(B) call mom").unwrap();
+        assert_eq!(task.priority, Some('B'));
+        assert_eq!(task.priority_span(), Some(0..3));
+        assert_eq!(task.description_span(), 4..12);
+        assert!(TaskRef::parse("   ").is_none());
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    /// Parse a line in both modes, expecting the same task from each
+    fn parse(line: &str) -> Task {
+        let task = TaskParser::new().parse_line(line).unwrap();
+        let strict = TaskParser::with_options(ParseOptions::strict());
+        assert_eq!(
+            strict.parse(line),
+            Ok((task.clone(), Vec::new())),
+            "{}",
+            line
+        );
+        task
+    }
+
+    // Examples from the todo.txt format specification,
+    // https://github.com/todotxt/todo.txt
+
+    #[test]
+    fn spec_priority() {
+        let task = parse("(A) Call Mom");
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.description(), "Call Mom");
+        assert_eq!(
+            parse("Really gotta call Mom (A) @phone @someday").priority,
+            None
+        );
+        assert_eq!(parse("(b) Get back to the boss").priority, None);
+        assert_eq!(parse("(B)->Submit TPS report").priority, None);
+    }
+
+    #[test]
+    fn spec_creation_date() {
+        let task = parse("2011-03-02 Document +TodoTxt task format");
+        assert_eq!(task.date_created, date(2011, 3, 2));
+        let task = parse("(A) 2011-03-02 Call Mom");
+        assert_eq!(
+            (task.priority, task.date_created),
+            (Some('A'), date(2011, 3, 2))
+        );
+        assert_eq!(parse("(A) Call Mom 2011-03-02").date_created, None);
+    }
+
+    #[test]
+    fn spec_contexts_and_projects() {
+        let task = parse("(A) Call Mom +Family +PeaceLoveAndHappiness @iphone @phone");
+        assert_eq!(task.projects(), ["Family", "PeaceLoveAndHappiness"]);
+        assert_eq!(task.contexts(), ["iphone", "phone"]);
+        assert!(parse("Email SoAndSo at soandso@example.com")
+            .contexts()
+            .is_empty());
+        assert!(parse("Learn how to add 2+2").projects().is_empty());
+    }
+
+    #[test]
+    fn spec_completion() {
+        assert!(!parse("xylophone lesson").completed);
+        assert!(!parse("X 2012-01-01 Make resolutions").completed);
+        let task = parse("(A) x Find ticket prices");
+        assert!(!task.completed);
+        assert_eq!(task.description(), "x Find ticket prices");
+
+        let task = parse("x 2011-03-02 2011-03-01 Review Tim's pull request +TodoTxtTouch @github");
+        assert!(task.completed);
+        assert_eq!(task.date_completed, date(2011, 3, 2));
+        assert_eq!(task.date_created, date(2011, 3, 1));
+
+        // A single date after the x is the creation date in either mode, so a
+        // file reads the same whether or not it is checked strictly
+        let line = "x 2011-03-03 Call Mom";
+        let lenient = TaskParser::new().parse_line(line).unwrap();
+        assert_eq!(
+            (lenient.date_completed, lenient.date_created),
+            (None, date(2011, 3, 3))
+        );
+        for line in [
+            line,
+            "x 2011-03-02 2011-03-01 Review",
+            "(A) 2011-03-01 Plan",
+        ] {
+            let strict = TaskParser::with_options(ParseOptions::strict())
+                .parse_line(line)
+                .unwrap();
+            assert_eq!(Ok(strict.clone()), Task::from_str(line));
+            assert_eq!(Ok(strict.clone()), Task::from_str(&strict.to_string()));
+        }
+    }
+
+    #[test]
+    fn spec_tags() {
+        assert_eq!(
+            parse("call mom due:2010-01-02").tag("due"),
+            Some("2010-01-02")
+        );
+        assert_eq!(parse("meet at 10:30:00").tags(), []);
+    }
+
+    #[test]
+    fn violations() {
+        let lenient = TaskParser::new();
+        let strict = TaskParser::with_options(ParseOptions::strict());
+        for (line, violation) in [
+            ("x (A) call mom", SpecViolation::PriorityOnCompletedTask),
+            (
+                "2023-11-30 2023-11-01 call mom",
+                SpecViolation::CompletionDateOnOpenTask,
+            ),
+            (
+                "x 2023-11-01 2023-11-30 call mom",
+                SpecViolation::CompletedBeforeCreated,
+            ),
+        ] {
+            let (_, violations) = lenient.parse(line).unwrap();
+            assert_eq!(violations, [violation]);
+            assert_eq!(
+                strict.parse(line),
+                Err(ParseTaskError::Violation(violation))
+            );
+        }
+        assert_eq!(strict.parse(" "), Err(ParseTaskError::Empty));
     }
 }
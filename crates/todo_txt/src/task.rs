@@ -7,6 +7,9 @@ use std::str::FromStr;
 //
 //------------------------------------------------------------------------------
 
+/// Tag keys listing the ids of tasks a task depends on, eg, `dep:3` or `p:3`
+pub const DEPENDENCY_TAG_KEYS: [&str; 2] = ["dep", "p"];
+
 #[derive(Debug, Default, Clone, PartialEq, PartialOrd, Ord, Eq)]
 pub struct Task {
     pub completed: bool,
@@ -17,6 +20,7 @@ pub struct Task {
     description: String,
     contexts: Vec<String>,
     projects: Vec<String>,
+    tags: Vec<(String, String)>,
 }
 
 impl std::fmt::Display for Task {
@@ -27,13 +31,20 @@ impl std::fmt::Display for Task {
         if let Some(p) = self.priority {
             write!(f, "({}) ", p)?;
         }
-        if let Some(d) = self.date_completed {
+        // A completion date is only written along with a creation date, as a
+        // single date is read back as the creation date
+        if let (Some(d), Some(_)) = (self.date_completed, self.date_created) {
             write!(f, "{} ", d)?;
         }
         if let Some(d) = self.date_created {
             write!(f, "{} ", d)?;
         }
-        write!(f, "{}", self.description)
+        // Escape a description which would otherwise be read back differently
+        if parse::needs_escape(self, &self.description) {
+            write!(f, "{}{}", parse::ESCAPE, self.description.trim_start())
+        } else {
+            write!(f, "{}", self.description)
+        }
     }
 }
 
@@ -41,12 +52,7 @@ impl FromStr for Task {
     type Err = parse::ParseTaskError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let task_parser = parse::TaskParser::new();
-        if let Some(task) = task_parser.parse_line(s) {
-            Ok(task)
-        } else {
-            Err(parse::ParseTaskError)
-        }
+        parse::TaskParser::new().parse(s).map(|(task, _)| task)
     }
 }
 
@@ -57,6 +63,9 @@ impl Task {
 
     pub fn set_description(&mut self, description: &str) {
         self.description = description.to_string();
+        self.contexts.clear();
+        self.projects.clear();
+        self.tags.clear();
         let tokens: std::collections::VecDeque<&str> =
             self.description.split_whitespace().collect();
         for token in tokens {
@@ -66,6 +75,8 @@ impl Task {
             } else if token.starts_with('+') {
                 self.projects
                     .push(token.strip_prefix('+').unwrap().to_string());
+            } else if let Some((key, value)) = parse::split_tag(token) {
+                self.tags.push((key.to_string(), value.to_string()));
             }
         }
     }
@@ -77,4 +88,424 @@ impl Task {
     pub fn projects(&self) -> &[String] {
         &self.projects
     }
+
+    /// All `key:value` tags found in the description, in order of appearance
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Value of the first tag with the given key
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Date given by the `due:` tag, if present and a valid date
+    pub fn due_date(&self) -> Option<chrono::NaiveDate> {
+        self.tag("due")
+            .and_then(|due| chrono::NaiveDate::parse_from_str(due, "%Y-%m-%d").ok())
+    }
+
+    /// Replace all `@context` words in the description with the given contexts,
+    /// which are appended to the end of the description
+    pub fn set_contexts(&mut self, contexts: &[String]) {
+        let words: Vec<String> = contexts.iter().map(|c| format!("@{}", c)).collect();
+        self.replace_words(|word| word.starts_with('@'), &words);
+    }
+
+    /// See [Self::set_contexts]
+    pub fn set_projects(&mut self, projects: &[String]) {
+        let words: Vec<String> = projects.iter().map(|p| format!("+{}", p)).collect();
+        self.replace_words(|word| word.starts_with('+'), &words);
+    }
+
+    /// See [Self::set_contexts]
+    pub fn set_tags(&mut self, tags: &[(String, String)]) {
+        let words: Vec<String> = tags.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+        self.replace_words(
+            |word| {
+                !word.starts_with('@') && !word.starts_with('+') && parse::split_tag(word).is_some()
+            },
+            &words,
+        );
+    }
+
+    /// Set the value of a tag, replacing the first existing tag with the same key
+    /// in place and removing any others.  A value of `None` removes the tag.
+    pub fn set_tag(&mut self, key: &str, value: Option<&str>) {
+        let mut replaced = false;
+        let mut words: Vec<String> = Vec::new();
+        for word in self.description.split_whitespace() {
+            match parse::split_tag(word) {
+                Some((k, _)) if k == key && !word.starts_with(['@', '+']) => {
+                    if let (Some(value), false) = (value, replaced) {
+                        words.push(format!("{}:{}", key, value));
+                        replaced = true;
+                    }
+                }
+                _ => words.push(word.to_string()),
+            }
+        }
+        if let (Some(value), false) = (value, replaced) {
+            words.push(format!("{}:{}", key, value));
+        }
+        self.set_description(&words.join(" "));
+    }
+
+    /// Add a context to the end of the description if not already present
+    pub fn add_context(&mut self, context: &str) {
+        if !self.contexts.iter().any(|c| c == context) {
+            self.append_word(&format!("@{}", context));
+        }
+    }
+
+    /// Remove all occurrences of a context from the description
+    pub fn remove_context(&mut self, context: &str) {
+        let word = format!("@{}", context);
+        self.replace_words(|w| w == word, &[]);
+    }
+
+    /// See [Self::add_context]
+    pub fn add_project(&mut self, project: &str) {
+        if !self.projects.iter().any(|p| p == project) {
+            self.append_word(&format!("+{}", project));
+        }
+    }
+
+    /// See [Self::remove_context]
+    pub fn remove_project(&mut self, project: &str) {
+        let word = format!("+{}", project);
+        self.replace_words(|w| w == word, &[]);
+    }
+
+    fn append_word(&mut self, word: &str) {
+        if self.description.trim().is_empty() {
+            self.set_description(word);
+        } else {
+            self.set_description(&format!("{} {}", self.description, word));
+        }
+    }
+
+    /// Remove all description words matching `is_match` and append `words`
+    fn replace_words<F: Fn(&str) -> bool>(&mut self, is_match: F, words: &[String]) {
+        let description = self
+            .description
+            .split_whitespace()
+            .filter(|word| !is_match(word))
+            .chain(words.iter().map(|word| word.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.set_description(&description);
+    }
+
+    /// Identifier given by the `id:` tag, referred to by dependent tasks
+    pub fn id(&self) -> Option<&str> {
+        self.tag("id")
+    }
+
+    /// Id of the parent task given by the `parent:` tag, making this a subtask
+    pub fn parent(&self) -> Option<&str> {
+        self.tag("parent")
+    }
+
+    /// Time recorded on the task by its `spent:` tag, eg, `spent:1h30m`
+    pub fn spent(&self) -> Option<chrono::Duration> {
+        crate::date::parse_duration(self.tag("spent")?)
+    }
+
+    /// Add to the time recorded by the `spent:` tag, which keeps the exact total
+    /// down to the second.  A total too long to represent is left unchanged.
+    pub fn add_spent(&mut self, duration: chrono::Duration) {
+        let spent = self.spent().unwrap_or_else(chrono::Duration::zero);
+        let Some(spent) = spent.checked_add(&duration) else {
+            return;
+        };
+        self.set_tag("spent", Some(&crate::date::format_exact_duration(spent)));
+    }
+
+    /// Ids of the tasks this task depends on, from its [DEPENDENCY_TAG_KEYS]
+    /// tags.  A tag may list several comma separated ids.
+    pub fn dependencies(&self) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|(key, _)| DEPENDENCY_TAG_KEYS.contains(&key.as_str()))
+            .flat_map(|(_, value)| value.split(','))
+            .filter(|id| !id.is_empty())
+            .collect()
+    }
+
+    /// Add a dependency on the task with the given id if not already present
+    pub fn add_dependency(&mut self, id: &str) {
+        let mut ids: Vec<String> = self.dependencies().iter().map(|d| d.to_string()).collect();
+        if !ids.iter().any(|d| d == id) {
+            ids.push(id.to_string());
+            self.set_dependencies(&ids);
+        }
+    }
+
+    /// Remove any dependency on the task with the given id
+    pub fn remove_dependency(&mut self, id: &str) {
+        let ids: Vec<String> = self
+            .dependencies()
+            .iter()
+            .filter(|d| **d != id)
+            .map(|d| d.to_string())
+            .collect();
+        self.set_dependencies(&ids);
+    }
+
+    /// Replace all dependency tags with a single tag listing `ids`, keeping the
+    /// key already in use
+    fn set_dependencies(&mut self, ids: &[String]) {
+        let key = DEPENDENCY_TAG_KEYS
+            .iter()
+            .find(|key| self.tag(key).is_some())
+            .copied()
+            .unwrap_or(DEPENDENCY_TAG_KEYS[0]);
+        for other in DEPENDENCY_TAG_KEYS.iter().filter(|other| **other != key) {
+            self.set_tag(other, None);
+        }
+        let value = ids.join(",");
+        self.set_tag(key, (!ids.is_empty()).then_some(value.as_str()));
+    }
+
+    /// Returns true if the task is still open and its due date is before `today`
+    pub fn is_overdue(&self, today: chrono::NaiveDate) -> bool {
+        !self.completed && self.due_date().is_some_and(|due| due < today)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_description_words() {
+        let mut task = Task::from_str("call bob @phone +work due:2023-12-01 x:y").unwrap();
+        assert_eq!(task.tags().len(), 2);
+        assert_eq!(
+            task.due_date(),
+            chrono::NaiveDate::from_ymd_opt(2023, 12, 1)
+        );
+
+        task.set_tag("due", Some("2024-01-01"));
+        task.remove_context("phone");
+        task.add_context("office");
+        task.add_project("work");
+        assert_eq!(
+            task.description(),
+            "call bob +work due:2024-01-01 x:y @office"
+        );
+        assert_eq!(task.contexts(), ["office"]);
+
+        task.set_tag("x", None);
+        task.set_projects(&["home".to_string()]);
+        assert_eq!(task.description(), "call bob due:2024-01-01 @office +home");
+        assert_eq!(task.tags(), [("due".to_string(), "2024-01-01".to_string())]);
+
+        // Words added to an emptied description are not preceded by a space
+        let mut other = Task::from_str("@desk").unwrap();
+        other.remove_context("desk");
+        other.add_project("work");
+        assert_eq!(other.description(), "+work");
+
+        // Short sessions add up without rounding
+        for _ in 0..4 {
+            task.add_spent(chrono::Duration::seconds(20));
+        }
+        assert_eq!(task.tag("spent"), Some("1m20s"));
+
+        // A total too long to represent is kept rather than overflowing
+        task.set_tag("spent", Some("2562047788015h"));
+        task.add_spent(chrono::Duration::hours(1));
+        assert_eq!(task.tag("spent"), Some("2562047788015h"));
+    }
+
+    #[test]
+    fn escaping() {
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 11, 30);
+        for (description, created, written) in [
+            (
+                "2023-11-30 was a good day",
+                None,
+                "\\2023-11-30 was a good day",
+            ),
+            ("(B) call mom", None, "\\(B) call mom"),
+            ("x marks the spot", None, "\\x marks the spot"),
+            ("\\x marks the spot", None, "\\\\x marks the spot"),
+            ("\\home dir", None, "\\home dir"),
+            ("(B) call mom", date, "2023-11-30 (B) call mom"),
+            ("x marks the spot", date, "2023-11-30 x marks the spot"),
+            (
+                "2023-11-30 was a good day",
+                date,
+                "2023-11-30 \\2023-11-30 was a good day",
+            ),
+        ] {
+            let mut task = Task {
+                date_created: created,
+                ..Default::default()
+            };
+            task.set_description(description);
+            assert_eq!(task.to_string(), written);
+            assert_eq!(Task::from_str(written), Ok(task));
+        }
+
+        // Only the escape of a word read as metadata is removed
+        let task = Task::from_str("(A) \\2023-11-30 \\(B)").unwrap();
+        assert_eq!(task.description(), "2023-11-30 \\(B)");
+        let task = Task::from_str("x \\(A) call").unwrap();
+        assert_eq!((task.completed, task.description()), (true, "(A) call"));
+    }
+
+    //--------------------------------------------------------------------------
+    //
+    // Round trip properties
+    //
+    //--------------------------------------------------------------------------
+
+    use proptest::prelude::*;
+
+    fn date() -> impl Strategy<Value = chrono::NaiveDate> {
+        (0i64..20_000).prop_map(|days| {
+            chrono::NaiveDate::from_ymd_opt(1990, 1, 1).unwrap() + chrono::Duration::days(days)
+        })
+    }
+
+    /// Words of a description, including ones which look like the completion
+    /// mark, a priority or a date
+    fn word() -> impl Strategy<Value = String> {
+        prop_oneof![
+            4 => "[a-zA-Z0-9.,!?'-]{1,8}",
+            1 => "@[a-z][a-z./]{0,6}",
+            1 => "\\+[a-z][a-z./]{0,6}",
+            1 => "[a-z]{1,5}:[a-z0-9-]{1,10}",
+            1 => Just("x".to_string()),
+            1 => "\\([A-Z]\\)",
+            1 => date().prop_map(|date| date.to_string()),
+            1 => "\\\\{1,2}(x|\\([A-Z]\\)|[a-z]{1,3})",
+        ]
+    }
+
+    /// Tasks with any combination of completion, priority and dates, including
+    /// ones the specification does not allow
+    fn task() -> impl Strategy<Value = Task> {
+        (
+            any::<bool>(),
+            proptest::option::of(proptest::char::range('A', 'Z')),
+            proptest::option::of(date()),
+            proptest::option::of(date()),
+            proptest::collection::vec(word(), 1..8),
+        )
+            .prop_map(
+                |(completed, priority, date_completed, date_created, words)| {
+                    let mut task = Task {
+                        completed,
+                        priority,
+                        date_completed,
+                        date_created,
+                        ..Default::default()
+                    };
+                    task.set_description(&words.join(" "));
+                    task
+                },
+            )
+    }
+
+    /// Changes made through the mutators which keep the contexts, projects and
+    /// tags in step with the description
+    #[derive(Debug, Clone)]
+    enum Edit {
+        SetTag(String, Option<String>),
+        AddContext(String),
+        RemoveContext(String),
+        AddProject(String),
+        RemoveProject(String),
+    }
+
+    fn edit() -> impl Strategy<Value = Edit> {
+        // Few names, so names are often removed after being added
+        let name = || "[a-c]";
+        prop_oneof![
+            ("[a-z]{1,3}", proptest::option::of("[a-z0-9-]{1,6}"))
+                .prop_map(|(key, value)| Edit::SetTag(key, value)),
+            name().prop_map(Edit::AddContext),
+            name().prop_map(Edit::RemoveContext),
+            name().prop_map(Edit::AddProject),
+            name().prop_map(Edit::RemoveProject),
+        ]
+    }
+
+    /// Check that `task` is read back as written, apart from a completion date
+    /// without a creation date, which is not written
+    fn check_round_trip(task: &Task) -> Result<(), TestCaseError> {
+        let mut expected = task.clone();
+        if expected.date_created.is_none() {
+            expected.date_completed = None;
+        }
+        let written = task.to_string();
+        if written.trim().is_empty() {
+            prop_assert_eq!(Task::from_str(&written), Err(parse::ParseTaskError::Empty));
+        } else {
+            prop_assert_eq!(Task::from_str(&written), Ok(expected));
+        }
+        Ok(())
+    }
+
+    /// Check the contexts, projects and tags of `task` against those found in
+    /// the description when it is written out and read back
+    fn check_names(task: &Task) -> Result<(), TestCaseError> {
+        // Nothing is read back from a task with nothing to write
+        let read = Task::from_str(&task.to_string()).unwrap_or_default();
+        prop_assert_eq!(task.contexts(), read.contexts());
+        prop_assert_eq!(task.projects(), read.projects());
+        prop_assert_eq!(task.tags(), read.tags());
+
+        let words: Vec<&str> = read.description().split_whitespace().collect();
+        let contexts: Vec<&str> = words.iter().filter_map(|w| w.strip_prefix('@')).collect();
+        let projects: Vec<&str> = words.iter().filter_map(|w| w.strip_prefix('+')).collect();
+        let tags: Vec<(String, String)> = words
+            .iter()
+            .filter(|w| !w.starts_with(['@', '+']))
+            .filter_map(|w| w.split_once(':'))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty() && !value.contains(':'))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        prop_assert_eq!(read.contexts(), contexts);
+        prop_assert_eq!(read.projects(), projects);
+        prop_assert_eq!(read.tags(), tags);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(task in task()) {
+            check_round_trip(&task)?;
+        }
+
+        #[test]
+        fn description_words(task in task()) {
+            check_names(&task)?;
+        }
+
+        #[test]
+        fn edited_description_words(
+            mut task in task(),
+            edits in proptest::collection::vec(edit(), 1..6),
+        ) {
+            for edit in edits {
+                match edit {
+                    Edit::SetTag(key, value) => task.set_tag(&key, value.as_deref()),
+                    Edit::AddContext(context) => task.add_context(&context),
+                    Edit::RemoveContext(context) => task.remove_context(&context),
+                    Edit::AddProject(project) => task.add_project(&project),
+                    Edit::RemoveProject(project) => task.remove_project(&project),
+                }
+                check_round_trip(&task)?;
+                check_names(&task)?;
+            }
+        }
+    }
 }
@@ -23,16 +23,307 @@ pub fn read<B: std::io::BufRead>(lines: &mut std::io::Lines<B>) -> Vec<Task> {
     tasks
 }
 
+//------------------------------------------------------------------------------
+//
+// project and context hierarchies
+//
+//------------------------------------------------------------------------------
+
+/// Separators used to nest projects and contexts, eg, `+work.backend.api` or
+/// `@home/garage`
+pub static HIERARCHY_SEPARATORS: [char; 2] = ['.', '/'];
+
+/// Returns true if `name` is `ancestor` itself or is nested anywhere beneath it
+pub fn is_within(name: &str, ancestor: &str) -> bool {
+    match name.strip_prefix(ancestor) {
+        Some(rest) => rest.is_empty() || rest.starts_with(HIERARCHY_SEPARATORS),
+        None => false,
+    }
+}
+
+/// The components of a nested name, eg, `work`, `backend` and `api` for
+/// `work.backend.api`.  Empty components, as in `.work` or `work..api`, are
+/// ignored.
+fn segments(name: &str) -> impl Iterator<Item = &str> {
+    name.split(HIERARCHY_SEPARATORS)
+        .filter(|segment| !segment.is_empty())
+}
+
+/// Nesting depth of a project or context name (zero for top level names)
+pub fn depth(name: &str) -> usize {
+    segments(name).count().saturating_sub(1)
+}
+
+/// The parent of a nested name, eg, `work.backend` for `work.backend.api`
+pub fn parent(name: &str) -> Option<&str> {
+    let name = name.trim_end_matches(HIERARCHY_SEPARATORS);
+    let pos = name.rfind(HIERARCHY_SEPARATORS)?;
+    let parent = name[..pos].trim_end_matches(HIERARCHY_SEPARATORS);
+    segments(parent).next().map(|_| parent)
+}
+
+/// The final component of a nested name, eg, `api` for `work.backend.api`
+pub fn leaf(name: &str) -> &str {
+    segments(name).last().unwrap_or(name)
+}
+
+/// Adds all implied ancestors to a list of names and sorts the result so that
+/// every name is directly followed by its descendants
+pub fn with_ancestors(names: &[String]) -> Vec<String> {
+    let mut all = std::collections::HashSet::new();
+    for name in names {
+        let mut name = name.as_str();
+        all.insert(name);
+        while let Some(p) = parent(name) {
+            all.insert(p);
+            name = p;
+        }
+    }
+
+    let mut all: Vec<&str> = all.into_iter().collect();
+    all.sort_unstable_by(|a, b| segments(a).cmp(segments(b)).then(a.cmp(b)));
+    all.into_iter().map(|name| name.to_string()).collect()
+}
+
+//------------------------------------------------------------------------------
+//
+// dependencies between tasks, via `id:` and `dep:` tags
+//
+//------------------------------------------------------------------------------
+
+/// A new id for a task, one more than the largest numeric id in use
+pub fn next_id<T: Borrow<Task>>(tasks: &[T]) -> String {
+    let max = tasks
+        .iter()
+        .filter_map(|task| task.borrow().id()?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    (max + 1).to_string()
+}
+
+/// Indices of the tasks a task directly depends on.  Unknown ids are ignored.
+pub fn dependencies_of<T: Borrow<Task>>(tasks: &[T], task: &Task) -> Vec<usize> {
+    let ids = task.dependencies();
+    tasks
+        .iter()
+        .map(|t| -> &Task { t.borrow() })
+        .enumerate()
+        .filter(|(_, t)| t.id().is_some_and(|id| ids.contains(&id)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Indices of the tasks directly depending on a task
+pub fn dependents_of<T: Borrow<Task>>(tasks: &[T], task: &Task) -> Vec<usize> {
+    let Some(id) = task.id() else {
+        return Vec::new();
+    };
+    tasks
+        .iter()
+        .map(|t| -> &Task { t.borrow() })
+        .enumerate()
+        .filter(|(_, t)| t.dependencies().contains(&id))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Indices of open tasks which depend on at least one open task
+pub fn blocked<T: Borrow<Task>>(tasks: &[T]) -> std::collections::HashSet<usize> {
+    let open_ids: std::collections::HashSet<&str> = tasks
+        .iter()
+        .map(|task| task.borrow())
+        .filter(|task| !task.completed)
+        .filter_map(|task| task.id())
+        .collect();
+    tasks
+        .iter()
+        .map(|task| -> &Task { task.borrow() })
+        .enumerate()
+        .filter(|(_, task)| {
+            !task.completed && task.dependencies().iter().any(|id| open_ids.contains(id))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Depth and index of every task the task at `idx` depends on, directly or
+/// indirectly, in depth first order.  Tasks reachable through several paths or
+/// cycles are listed once.
+pub fn dependency_tree<T: Borrow<Task>>(tasks: &[T], idx: usize) -> Vec<(usize, usize)> {
+    let mut tree = Vec::new();
+    let mut visited = std::collections::HashSet::from([idx]);
+    let mut stack: Vec<(usize, usize)> = dependencies_of(tasks, tasks[idx].borrow())
+        .into_iter()
+        .rev()
+        .map(|dep| (1, dep))
+        .collect();
+    while let Some((depth, dep)) = stack.pop() {
+        if !visited.insert(dep) {
+            continue;
+        }
+        tree.push((depth, dep));
+        stack.extend(
+            dependencies_of(tasks, tasks[dep].borrow())
+                .into_iter()
+                .rev()
+                .map(|next| (depth + 1, next)),
+        );
+    }
+    tree
+}
+
+//------------------------------------------------------------------------------
+//
+// subtasks, via `parent:` tags naming the `id:` of the parent task
+//
+//------------------------------------------------------------------------------
+
+/// Index of the parent of a task, if its parent exists
+pub fn parent_of<T: Borrow<Task>>(tasks: &[T], task: &Task) -> Option<usize> {
+    let parent = task.parent()?;
+    tasks.iter().position(|t| t.borrow().id() == Some(parent))
+}
+
+/// Index of the task with each id, for finding parents without scanning all
+/// tasks.  As in [parent_of], the first task with an id is used.
+fn id_indices<T: Borrow<Task>>(tasks: &[T]) -> std::collections::HashMap<&str, usize> {
+    let mut ids = std::collections::HashMap::new();
+    for (idx, task) in tasks.iter().enumerate() {
+        if let Some(id) = task.borrow().id() {
+            ids.entry(id).or_insert(idx);
+        }
+    }
+    ids
+}
+
+/// Indices of the direct subtasks of a task
+pub fn children_of<T: Borrow<Task>>(tasks: &[T], task: &Task) -> Vec<usize> {
+    let Some(id) = task.id() else {
+        return Vec::new();
+    };
+    tasks
+        .iter()
+        .map(|t| -> &Task { t.borrow() })
+        .enumerate()
+        .filter(|(_, t)| t.parent() == Some(id))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Indices of the parent, grandparent, etc of the task at `idx`, nearest first.
+/// A cycle of parents ends the list.
+pub fn ancestors_of<T: Borrow<Task>>(tasks: &[T], idx: usize) -> Vec<usize> {
+    ancestors_by_id(tasks, &id_indices(tasks), idx)
+}
+
+/// [ancestors_of] with the index of each task id already known
+fn ancestors_by_id<T: Borrow<Task>>(
+    tasks: &[T],
+    ids: &std::collections::HashMap<&str, usize>,
+    idx: usize,
+) -> Vec<usize> {
+    let mut ancestors = Vec::new();
+    let mut current = idx;
+    while let Some(&parent) = tasks[current]
+        .borrow()
+        .parent()
+        .and_then(|parent| ids.get(parent))
+    {
+        if parent == idx || ancestors.contains(&parent) {
+            break;
+        }
+        ancestors.push(parent);
+        current = parent;
+    }
+    ancestors
+}
+
+/// Indices of all subtasks of the task at `idx`, directly or indirectly, in
+/// depth first order
+pub fn descendants_of<T: Borrow<Task>>(tasks: &[T], idx: usize) -> Vec<usize> {
+    let mut descendants = Vec::new();
+    let mut visited = std::collections::HashSet::from([idx]);
+    let mut stack: Vec<usize> = children_of(tasks, tasks[idx].borrow());
+    stack.reverse();
+    while let Some(child) = stack.pop() {
+        if !visited.insert(child) {
+            continue;
+        }
+        descendants.push(child);
+        stack.extend(children_of(tasks, tasks[child].borrow()).into_iter().rev());
+    }
+    descendants
+}
+
+/// Depth and index of the tasks at `indices`, ordered so that subtasks follow
+/// their parent.  Tasks whose parent is not among `indices` are at depth 0, and
+/// otherwise keep their order in `indices`.
+pub fn outline<T: Borrow<Task>>(tasks: &[T], indices: &[usize]) -> Vec<(usize, usize)> {
+    let listed: std::collections::HashSet<usize> = indices.iter().copied().collect();
+    let ids = id_indices(tasks);
+
+    // Listed subtasks of each listed task, in the order of `indices`
+    let mut roots = Vec::new();
+    let mut children: std::collections::HashMap<usize, Vec<usize>> =
+        std::collections::HashMap::new();
+    for idx in indices {
+        let parent = tasks[*idx]
+            .borrow()
+            .parent()
+            .and_then(|parent| ids.get(parent))
+            .filter(|parent| listed.contains(parent));
+        match parent {
+            Some(parent) => children.entry(*parent).or_default().push(*idx),
+            None => roots.push(*idx),
+        }
+    }
+
+    let mut outline = Vec::with_capacity(indices.len());
+    let mut visited = std::collections::HashSet::new();
+    // Tasks in a cycle of parents have no root, so are added last at depth 0
+    for root in roots.into_iter().chain(indices.iter().copied()) {
+        let mut stack = vec![(0, root)];
+        while let Some((depth, idx)) = stack.pop() {
+            if !visited.insert(idx) {
+                continue;
+            }
+            outline.push((depth, idx));
+            if let Some(children) = children.get(&idx) {
+                stack.extend(children.iter().rev().map(|child| (depth + 1, *child)));
+            }
+        }
+    }
+    outline
+}
+
+//------------------------------------------------------------------------------
+//
+// filtering
+//
+//------------------------------------------------------------------------------
+
 pub struct TasksFilter<'a> {
     pub tasks: Vec<&'a Task>,
     pub task_indices: Vec<usize>,
+    all_tasks: &'a [Task],
 }
 
 impl<'a> TasksFilter<'a> {
-    pub fn new(tasks: &'a [Task]) -> TasksFilter {
+    pub fn new(tasks: &'a [Task]) -> TasksFilter<'a> {
         TasksFilter {
             tasks: tasks.iter().collect(),
             task_indices: (0..tasks.len()).collect(),
+            all_tasks: tasks,
+        }
+    }
+
+    /// Start from the tasks at the given indices, eg, the result of a previous
+    /// filter, so it can be refined without filtering all tasks again
+    pub fn with_indices(tasks: &'a [Task], task_indices: Vec<usize>) -> TasksFilter<'a> {
+        TasksFilter {
+            tasks: task_indices.iter().map(|idx| &tasks[*idx]).collect(),
+            task_indices,
+            all_tasks: tasks,
         }
     }
     pub fn num_contexts(&self, include_completed: bool) -> usize {
@@ -68,7 +359,7 @@ impl<'a> TasksFilter<'a> {
                 .zip(self.task_indices)
                 .filter(|(task, _index)| {
                     for c in task.contexts() {
-                        if c == context {
+                        if is_within(c, context) {
                             return true;
                         }
                     }
@@ -89,7 +380,7 @@ impl<'a> TasksFilter<'a> {
                 .zip(self.task_indices)
                 .filter(|(task, _index)| {
                     for p in task.projects() {
-                        if p == project {
+                        if is_within(p, project) {
                             return true;
                         }
                     }
@@ -116,6 +407,45 @@ impl<'a> TasksFilter<'a> {
         self
     }
 
+    /// Keep only tasks whose contexts and projects are all among the given
+    /// ones, eg, tasks without any context or project if both are empty
+    pub fn with_only(mut self, names_opt: Option<(&[String], &[String])>) -> TasksFilter<'a> {
+        if let Some((contexts, projects)) = names_opt {
+            let tasks_with_indices: Vec<(&Task, usize)> = self
+                .tasks
+                .into_iter()
+                .zip(self.task_indices)
+                .filter(|(task, _index)| {
+                    task.contexts().iter().all(|c| contexts.contains(c))
+                        && task.projects().iter().all(|p| projects.contains(p))
+                })
+                .collect();
+
+            (self.tasks, self.task_indices) = tasks_with_indices.into_iter().unzip();
+        }
+        self
+    }
+
+    /// Add back the ancestors of the remaining tasks, so subtasks are shown in
+    /// the context of their parents
+    pub fn with_ancestors(mut self, include_ancestors: bool) -> TasksFilter<'a> {
+        if include_ancestors {
+            let ids = id_indices(self.all_tasks);
+            let mut indices: std::collections::BTreeSet<usize> =
+                self.task_indices.iter().copied().collect();
+            for idx in &self.task_indices {
+                indices.extend(ancestors_by_id(self.all_tasks, &ids, *idx));
+            }
+            self.task_indices = indices.into_iter().collect();
+            self.tasks = self
+                .task_indices
+                .iter()
+                .map(|idx| &self.all_tasks[*idx])
+                .collect();
+        }
+        self
+    }
+
     pub fn without_completed(mut self, omit_completed: bool) -> TasksFilter<'a> {
         if omit_completed {
             let tasks_with_indices: Vec<(&Task, usize)> = self
@@ -224,6 +554,89 @@ pub fn collect_priorities<T: Borrow<Task>>(tasks: &[T], include_completed: bool)
     priorities
 }
 
+/// Number of open and completed tasks in some group of tasks
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TaskCounts {
+    pub open: usize,
+    pub done: usize,
+    pub overdue: usize,
+}
+
+impl TaskCounts {
+    pub fn total(&self) -> usize {
+        self.open + self.done
+    }
+
+    fn add(&mut self, task: &Task, today: chrono::NaiveDate) {
+        if task.completed {
+            self.done += 1;
+        } else {
+            self.open += 1;
+            if task.is_overdue(today) {
+                self.overdue += 1;
+            }
+        }
+    }
+}
+
+/// Count open, completed and overdue tasks
+pub fn count_tasks<T: Borrow<Task>>(tasks: &[T], today: chrono::NaiveDate) -> TaskCounts {
+    let mut counts = TaskCounts::default();
+    for task in tasks {
+        counts.add(task.borrow(), today);
+    }
+    counts
+}
+
+/// Count tasks per context.  A task counts towards each of its contexts and
+/// each of their ancestors, see [with_ancestors].
+pub fn count_contexts<T: Borrow<Task>>(
+    tasks: &[T],
+    today: chrono::NaiveDate,
+) -> std::collections::HashMap<String, TaskCounts> {
+    count_nested(tasks, today, Task::contexts)
+}
+
+/// See [count_contexts]
+pub fn count_projects<T: Borrow<Task>>(
+    tasks: &[T],
+    today: chrono::NaiveDate,
+) -> std::collections::HashMap<String, TaskCounts> {
+    count_nested(tasks, today, Task::projects)
+}
+
+pub fn count_priorities<T: Borrow<Task>>(
+    tasks: &[T],
+    today: chrono::NaiveDate,
+) -> std::collections::HashMap<char, TaskCounts> {
+    let mut counts: std::collections::HashMap<char, TaskCounts> = std::collections::HashMap::new();
+    for task in tasks {
+        if let Some(priority) = task.borrow().priority {
+            counts
+                .entry(priority)
+                .or_default()
+                .add(task.borrow(), today);
+        }
+    }
+    counts
+}
+
+fn count_nested<T: Borrow<Task>>(
+    tasks: &[T],
+    today: chrono::NaiveDate,
+    names: fn(&Task) -> &[String],
+) -> std::collections::HashMap<String, TaskCounts> {
+    let mut counts: std::collections::HashMap<String, TaskCounts> =
+        std::collections::HashMap::new();
+    for task in tasks {
+        let task = task.borrow();
+        for name in with_ancestors(names(task)) {
+            counts.entry(name).or_default().add(task, today);
+        }
+    }
+    counts
+}
+
 pub fn include_completed<T: Borrow<Task>>(tasks: &[T], include_completed: bool) -> Vec<Task> {
     tasks
         .iter()
@@ -239,7 +652,7 @@ pub fn with_context<T: Borrow<Task>>(tasks: &[T], context: &str) -> Vec<Task> {
         .map(|borrow| borrow.borrow())
         .filter(|task| {
             for p in task.contexts() {
-                if p == context {
+                if is_within(p, context) {
                     return true;
                 }
             }
@@ -255,7 +668,7 @@ pub fn with_project<T: Borrow<Task>>(tasks: &[T], project: &str) -> Vec<Task> {
         .map(|borrow| borrow.borrow())
         .filter(|task| {
             for p in task.projects() {
-                if p == project {
+                if is_within(p, project) {
                     return true;
                 }
             }
@@ -276,10 +689,162 @@ pub fn with_priority<T: Borrow<Task>>(tasks: &[T], priority: char) -> Vec<Task>
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn filters() {
+        let tasks: Vec<Task> = [
+            "(A) call mom @phone +family",
+            "x (A) pay rent @home",
+            "(B) fix fence @home.garden +house",
+            "read book",
+        ]
+        .iter()
+        .map(|line| Task::from_str(line).unwrap())
+        .collect();
+
+        let filter = TasksFilter::new(&tasks)
+            .without_completed(true)
+            .with_context(Some("home"));
+        assert_eq!(filter.task_indices, [2]);
+        let filter = TasksFilter::new(&tasks)
+            .with_priority(Some('A'))
+            .with_project(Some("family"));
+        assert_eq!(filter.task_indices, [0]);
+        let filter = TasksFilter::with_indices(&tasks, vec![1, 2, 3]).with_priority(Some('A'));
+        assert_eq!(filter.task_indices, [1]);
+        let filter = TasksFilter::new(&tasks).with_only(Some((&[], &[])));
+        assert_eq!(filter.task_indices, [3]);
+
+        assert_eq!(
+            collect_contexts(&tasks, true),
+            ["home", "home.garden", "phone"]
+        );
+        assert_eq!(collect_contexts(&tasks, false), ["home.garden", "phone"]);
+        assert_eq!(collect_projects(&tasks, true), ["family", "house"]);
+        assert_eq!(collect_priorities(&tasks, false), ['A', 'B']);
+    }
+
+    #[test]
+    fn hierarchy() {
+        assert!(is_within("work.backend.api", "work"));
+        assert!(is_within("work/backend", "work"));
+        assert!(is_within("work", "work"));
+        assert!(!is_within("workshop", "work"));
+        assert!(!is_within("work", "work.backend"));
+
+        let names: Vec<String> = ["work-x", "work.backend.api", "home"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            with_ancestors(&names),
+            ["home", "work", "work.backend", "work.backend.api", "work-x"]
+        );
+
+        // Empty components don't add nodes to the tree
+        assert_eq!(parent(".work"), None);
+        assert_eq!(parent("work..api"), Some("work"));
+        assert_eq!(parent("work.api/"), Some("work"));
+        assert_eq!((depth(".work"), depth("work..api")), (0, 1));
+        assert_eq!((leaf(".work"), leaf("work.api/")), ("work", "api"));
+        let names: Vec<String> = [".home", "work..api"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(with_ancestors(&names), [".home", "work", "work..api"]);
+    }
 
     #[test]
-    fn it_works() {
-        assert_eq!(4, 4);
+    fn dependencies() {
+        let mut tasks: Vec<Task> = [
+            "design id:1",
+            "build id:2 dep:1",
+            "x test id:3",
+            "ship dep:2,3",
+            "loop id:4 p:4",
+        ]
+        .iter()
+        .map(|line| Task::from_str(line).unwrap())
+        .collect();
+
+        assert_eq!(next_id(&tasks), "5");
+        assert_eq!(dependencies_of(&tasks, &tasks[3]), [1, 2]);
+        assert_eq!(dependents_of(&tasks, &tasks[0]), [1]);
+        assert_eq!(dependency_tree(&tasks, 3), [(1, 1), (2, 0), (1, 2)]);
+        assert_eq!(dependency_tree(&tasks, 4), []);
+
+        let mut blocked_tasks: Vec<usize> = blocked(&tasks).into_iter().collect();
+        blocked_tasks.sort();
+        assert_eq!(blocked_tasks, [1, 3, 4]);
+
+        // Completing a task unblocks its dependents
+        tasks[0].completed = true;
+        assert!(!blocked(&tasks).contains(&1));
+
+        tasks[3].remove_dependency("3");
+        tasks[3].add_dependency("1");
+        assert_eq!(tasks[3].description(), "ship dep:2,1");
+        tasks[4].remove_dependency("4");
+        assert_eq!(tasks[4].description(), "loop id:4");
+    }
+
+    #[test]
+    fn subtasks() {
+        let tasks: Vec<Task> = [
+            "launch id:1",
+            "design id:2 parent:1",
+            "sketch parent:2 @home",
+            "build parent:1",
+            "other",
+        ]
+        .iter()
+        .map(|line| Task::from_str(line).unwrap())
+        .collect();
+
+        assert_eq!(parent_of(&tasks, &tasks[2]), Some(1));
+        assert_eq!(children_of(&tasks, &tasks[0]), [1, 3]);
+        assert_eq!(ancestors_of(&tasks, 2), [1, 0]);
+        assert_eq!(descendants_of(&tasks, 0), [1, 2, 3]);
+        assert_eq!(
+            outline(&tasks, &[4, 3, 2, 1, 0]),
+            [(0, 4), (0, 0), (1, 3), (1, 1), (2, 2)]
+        );
+        assert_eq!(outline(&tasks, &[2, 3]), [(0, 2), (0, 3)]);
+
+        let filter = TasksFilter::new(&tasks)
+            .with_context(Some("home"))
+            .with_ancestors(true);
+        assert_eq!(filter.task_indices, [0, 1, 2]);
+
+        // Tasks in a cycle of parents are still listed once each
+        let tasks: Vec<Task> = ["a id:1 parent:2", "b id:2 parent:1"]
+            .iter()
+            .map(|line| Task::from_str(line).unwrap())
+            .collect();
+        assert_eq!(ancestors_of(&tasks, 0), [1]);
+        assert_eq!(outline(&tasks, &[0, 1]), [(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn counts() {
+        let tasks: Vec<Task> = [
+            "x 2023-12-01 2023-11-01 ship it +work.api",
+            "2023-11-01 write docs +work.docs +work.api due:2023-11-15",
+            "(A) plan +home due:2024-01-01",
+        ]
+        .iter()
+        .map(|line| Task::from_str(line).unwrap())
+        .collect();
+        let today = chrono::NaiveDate::from_ymd_opt(2023, 12, 1).unwrap();
+
+        let summary = count_tasks(&tasks, today);
+        assert_eq!((summary.open, summary.done, summary.overdue), (2, 1, 1));
+
+        let projects = count_projects(&tasks, today);
+        assert_eq!(projects["work"].total(), 2);
+        assert_eq!(projects["work.api"].done, 1);
+        assert_eq!(projects["home"].open, 1);
+        assert_eq!(count_priorities(&tasks, today)[&'A'].open, 1);
     }
 }
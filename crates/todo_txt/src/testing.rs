@@ -0,0 +1,173 @@
+//! Generated and bundled todo.txt files for benchmarks, behind the `testing`
+//! feature.  The generated tasks only depend on the number of tasks and the
+//! seed, so benchmarks can generate the same tasks each run.
+
+/// Number of tasks in the generated todo.txt file of [todo_files]
+pub const GENERATED_LINES: usize = 100_000;
+
+const WORDS: [&str; 48] = [
+    "call",
+    "email",
+    "write",
+    "review",
+    "plan",
+    "fix",
+    "buy",
+    "book",
+    "clean",
+    "read",
+    "update",
+    "schedule",
+    "report",
+    "budget",
+    "garden",
+    "car",
+    "dentist",
+    "meeting",
+    "notes",
+    "slides",
+    "invoice",
+    "taxes",
+    "groceries",
+    "laundry",
+    "backup",
+    "server",
+    "release",
+    "draft",
+    "proposal",
+    "feedback",
+    "design",
+    "tests",
+    "docs",
+    "plumber",
+    "tickets",
+    "flight",
+    "hotel",
+    "gift",
+    "birthday",
+    "paint",
+    "fence",
+    "library",
+    "bank",
+    "insurance",
+    "doctor",
+    "recipe",
+    "bike",
+    "windows",
+];
+
+const CONTEXTS: [&str; 6] = [
+    "@work",
+    "@home",
+    "@phone",
+    "@errands",
+    "@work.office",
+    "@home/garage",
+];
+
+const PROJECTS: [&str; 8] = [
+    "+projA",
+    "+projB",
+    "+projC",
+    "+projD",
+    "+projE",
+    "+work.backend",
+    "+work.backend.api",
+    "+home.renovation",
+];
+
+/// Small xorshift generator, so no external crates are needed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// True with the given percent chance
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// One random task line, numbered `n` so tasks can refer to earlier ones
+pub fn generate_line(rng: &mut Rng, n: usize) -> String {
+    let start = chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    let created = start + chrono::Duration::days(rng.below(4 * 365) as i64);
+
+    let mut words = Vec::new();
+    if rng.chance(70) {
+        let completed = created + chrono::Duration::days(rng.below(60) as i64);
+        words.push(format!("x {}", completed));
+    } else if rng.chance(50) {
+        words.push(format!("({})", rng.pick(&["A", "B", "C", "D"])));
+    }
+    words.push(created.to_string());
+
+    for _ in 0..3 + rng.below(6) {
+        words.push(rng.pick(&WORDS).to_string());
+    }
+    for names in [&CONTEXTS[..], &PROJECTS[..]] {
+        for _ in 0..rng.below(3) {
+            let name = rng.pick(names).to_string();
+            if !words.contains(&name) {
+                words.push(name);
+            }
+        }
+    }
+    if rng.chance(20) {
+        let due = created + chrono::Duration::days(rng.below(90) as i64);
+        words.push(format!("due:{}", due));
+    }
+    if rng.chance(10) {
+        words.push(format!("id:{}", n));
+        if n > 0 && rng.chance(50) {
+            words.push(format!("dep:{}", rng.below(n)));
+        }
+    }
+    words.join(" ")
+}
+
+/// `lines` random tasks, one per line
+pub fn generate(lines: usize, seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut text = String::new();
+    for n in 0..lines {
+        text.push_str(&generate_line(&mut rng, n));
+        text.push('\n');
+    }
+    text
+}
+
+/// Todo.txt files bundled with the repository, from a few hundred to ten
+/// thousand tasks, and a generated one of [GENERATED_LINES] tasks, by name
+pub fn todo_files() -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = ["medium_todo.txt", "large_todo.txt"]
+        .iter()
+        .map(|name| {
+            let path = format!("{}/../../data/{}", env!("CARGO_MANIFEST_DIR"), name);
+            (name.to_string(), std::fs::read_to_string(path).unwrap())
+        })
+        .collect();
+    files.push((
+        format!("generated_{}", GENERATED_LINES),
+        generate(GENERATED_LINES, 1),
+    ));
+    files
+}
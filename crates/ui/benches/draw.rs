@@ -0,0 +1,46 @@
+use std::io::BufRead;
+
+/// Tasks of the todo.txt files bundled with the repository, from a few hundred
+/// to ten thousand tasks, and of a generated one, by name
+fn todo_files() -> Vec<(String, Vec<todo_txt::task::Task>)> {
+    todo_txt::testing::todo_files()
+        .into_iter()
+        .map(|(name, text)| (name, todo_txt::read_tasks(&mut text.as_bytes().lines())))
+        .collect()
+}
+
+fn draw(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("draw");
+    for (name, tasks) in todo_files() {
+        let mut app = app::App::new("kdo", &name, &tasks);
+        let mut ui_state = ui::state::State::new();
+        let backend = ratatui::backend::TestBackend::new(160, 50);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+
+        // A frame drawn after moving down the task list
+        group.bench_function(&name, |b| {
+            b.iter(|| {
+                app.navigate_down();
+                terminal
+                    .draw(|frame| ui::draw::draw(frame, &mut app, &mut ui_state))
+                    .unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn filter(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("filter");
+    for (name, tasks) in todo_files() {
+        let mut app = app::App::new("kdo", &name, &tasks);
+        app.navigate_right();
+
+        // Moving through the contexts pane, refiltering the task list each time
+        group.bench_function(&name, |b| b.iter(|| app.navigate_down()));
+    }
+    group.finish();
+}
+
+criterion::criterion_group!(benches, draw, filter);
+criterion::criterion_main!(benches);
@@ -1,4 +1,6 @@
 use crate::state;
+use ::app::report::{Report, REPORT_DAYS, REPORT_WEEKS};
+use ::app::timelog::TimeReport;
 use app::app;
 
 // Can choose arbitrary rgb value as such:
@@ -8,6 +10,10 @@ static FOCUS_COLOR : ratatui::style::Color = ratatui::style::Color::Reset;
 static UNFOCUS_COLOR : ratatui::style::Color = ratatui::style::Color::DarkGray; 
 static BG_COLOR : ratatui::style::Color    = ratatui::style::Color::Reset; 
 static SELECTION_COLOR : ratatui::style::Color = ratatui::style::Color::Yellow; 
+static OVERDUE_COLOR: ratatui::style::Color = ratatui::style::Color::Red;
+static ERROR_COLOR: ratatui::style::Color = ratatui::style::Color::Red;
+static BLOCKED_COLOR: ratatui::style::Color = ratatui::style::Color::DarkGray;
+static MARK_SYMBOL: &str = "*";
 
 pub fn draw<B: ratatui::backend::Backend>(
     frame: &mut ratatui::Frame<B>,
@@ -27,6 +33,13 @@ pub fn draw<B: ratatui::backend::Backend>(
     ui_state
         .priority_list_state
         .select(app.priority_list().selection());
+    ui_state
+        .detail_list_state
+        .select(if app.mode() == app::Mode::Detail {
+            app.field_list().selection()
+        } else {
+            None
+        });
 
     //
     // Create main body chunks
@@ -56,6 +69,27 @@ pub fn draw<B: ratatui::backend::Backend>(
         );
     frame.render_widget(header_block, chunks[0]);
 
+    // Running timer, right aligned within the header
+    if let Some((task, elapsed)) = app.timer() {
+        let seconds = elapsed.num_seconds().max(0);
+        let timer = format!(
+            "{}:{:02}:{:02} {} ",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            task.description()
+        );
+        let timer_area = ratatui::widgets::Block::default()
+            .borders(ratatui::widgets::Borders::ALL)
+            .inner(chunks[0]);
+        frame.render_widget(
+            ratatui::widgets::Paragraph::new(timer)
+                .style(ratatui::style::Style::default().fg(SELECTION_COLOR))
+                .alignment(ratatui::layout::Alignment::Right),
+            timer_area,
+        );
+    }
+
     if app.mode() == app::Mode::Help {
         //
         // Render only help info in the main chunk
@@ -67,6 +101,16 @@ pub fn draw<B: ratatui::backend::Backend>(
                     .borders(ratatui::widgets::Borders::ALL),
             );
         frame.render_widget(help_paragraph, chunks[1]);
+    } else if let (app::Mode::Report, Some(report)) = (app.mode(), app.report()) {
+        //
+        // Render only statistics in the main chunk
+        //
+        draw_report(frame, report, chunks[1]);
+    } else if let (app::Mode::TimeReport, Some(time_report)) = (app.mode(), app.time_report()) {
+        //
+        // Render only time spent in the main chunk
+        //
+        draw_time_report(frame, time_report, chunks[1]);
     } else {
         //
         // Body: main todo browser
@@ -84,50 +128,172 @@ pub fn draw<B: ratatui::backend::Backend>(
             )
             .split(chunks[1]);
 
-        // We can now render the item list
-        let tasks: Vec<String> = app
-            .task_list()
-            .items()
+        // We can now render the item list.  When viewing several files, prefix
+        // each task with a column naming the file it belongs to.  When tasks are
+        // marked, prefix each task with a mark column.
+        let source_width = if app.sources().len() > 1 {
+            (0..app.sources().len())
+                .map(|source| app.source_name(source).len())
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        // Optionally split the task pane to show details of the selected task
+        let task_chunks = ratatui::layout::Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints(
+                [
+                    ratatui::layout::Constraint::Min(3), // tasks
+                    ratatui::layout::Constraint::Length(if app.show_detail() {
+                        ::app::detail::TaskField::ALL.len() as u16 + 2
+                    } else {
+                        0
+                    }), // detail
+                ]
+                .as_ref(),
+            )
+            .split(body_chunks[0]);
+
+        // Only the tasks which fit in the pane are formatted
+        let window = visible_window(
+            &mut ui_state.task_list_state,
+            app.task_list().items().len(),
+            task_chunks[0].height.saturating_sub(2) as usize,
+        );
+        let mut window_state = ratatui::widgets::ListState::default().with_selected(
+            ui_state
+                .task_list_state
+                .selected()
+                .map(|selected| selected - window.start),
+        );
+
+        let task_items: Vec<ratatui::widgets::ListItem> = app.task_list().items()[window.clone()]
             .iter()
-            .map(|idx| app.tasks()[*idx].to_string())
-            .collect();
+            .zip(window)
+            .map(|(idx, pos)| {
+                // Indent subtasks beneath their parents, marking collapsed parents
+                let text = if app.outline() && *idx != 0 {
+                    let marker = if !app.has_subtasks(*idx) {
+                        "  "
+                    } else if app.is_collapsed_task(*idx) {
+                        "▸ "
+                    } else {
+                        "▾ "
+                    };
+                    format!(
+                        "{:indent$}{}{}",
+                        "",
+                        marker,
+                        app.task_string(*idx),
+                        indent = 2 * app.task_depth(pos)
+                    )
+                } else {
+                    app.task_string(*idx).to_string()
+                };
+                let task = if source_width == 0 {
+                    text
+                } else if *idx == 0 {
+                    format!("{:w$} {}", "", text, w = source_width)
+                } else {
+                    format!(
+                        "{:w$} {}",
+                        app.source_name(app.task_source(*idx)),
+                        text,
+                        w = source_width
+                    )
+                };
+                let task = match (app.marked_count(), app.is_marked(*idx)) {
+                    (0, _) => task,
+                    (_, true) => format!("{} {}", MARK_SYMBOL, task),
+                    (_, false) => format!("  {}", task),
+                };
 
+                // Dim tasks blocked by open dependencies
+                let item = ratatui::widgets::ListItem::new(task);
+                if app.is_blocked(*idx) {
+                    item.style(ratatui::style::Style::default().fg(BLOCKED_COLOR))
+                } else {
+                    item
+                }
+            })
+            .collect();
         frame.render_stateful_widget(
-            render_list(
-                "task",
-                &tasks,
+            render_items(
+                if app.inbox_only() { "inbox" } else { "task" },
+                task_items,
                 app.mode() == app::Mode::Normal && app.focus() == app::Focus::Tasks,
             ),
-            body_chunks[0],
-            &mut ui_state.task_list_state,
+            task_chunks[0],
+            &mut window_state,
         );
 
-        frame.render_stateful_widget(
-            render_list(
-                "context",
-                app.context_list().items(),
-                app.mode() == app::Mode::Normal && app.focus() == app::Focus::Contexts,
-            ),
+        if app.show_detail() {
+            let details: Vec<String> = match app.get_selected_task() {
+                Some(task) => {
+                    let today = app.today();
+                    app.field_list()
+                        .items()
+                        .iter()
+                        .map(|field| format!("{:12}{}", field.name(), field.display(task, today)))
+                        .collect()
+                }
+                None => vec!["no task selected".to_string()],
+            };
+            frame.render_stateful_widget(
+                render_list("detail", &details, app.mode() == app::Mode::Detail),
+                task_chunks[1],
+                &mut ui_state.detail_list_state,
+            );
+        }
+
+        // Side panes only format the names which fit in them, like the tasks
+        draw_side_pane(
+            frame,
+            "context",
+            app.context_list().items(),
+            |context| {
+                with_counts(
+                    &app.context_label(context),
+                    app.context_counts(context),
+                    false,
+                    body_chunks[1].width,
+                )
+            },
+            app.mode() == app::Mode::Normal && app.focus() == app::Focus::Contexts,
             body_chunks[1],
             &mut ui_state.context_list_state,
         );
-
-        frame.render_stateful_widget(
-            render_list(
-                "project",
-                app.project_list().items(),
-                app.mode() == app::Mode::Normal && app.focus() == app::Focus::Projects,
-            ),
+        draw_side_pane(
+            frame,
+            "project",
+            app.project_list().items(),
+            |project| {
+                with_counts(
+                    &app.project_label(project),
+                    app.project_counts(project),
+                    true,
+                    body_chunks[2].width,
+                )
+            },
+            app.mode() == app::Mode::Normal && app.focus() == app::Focus::Projects,
             body_chunks[2],
             &mut ui_state.project_list_state,
         );
-
-        frame.render_stateful_widget(
-            render_list(
-                "priority",
-                app.priority_list().items(),
-                app.mode() == app::Mode::Normal && app.focus() == app::Focus::Priorities,
-            ),
+        draw_side_pane(
+            frame,
+            "priority",
+            app.priority_list().items(),
+            |priority| {
+                with_counts(
+                    priority,
+                    app.priority_counts(priority),
+                    false,
+                    body_chunks[3].width,
+                )
+            },
+            app.mode() == app::Mode::Normal && app.focus() == app::Focus::Priorities,
             body_chunks[3],
             &mut ui_state.priority_list_state,
         );
@@ -136,43 +302,842 @@ pub fn draw<B: ratatui::backend::Backend>(
     //
     // Edit line at bottom
     //
+    let bulk_prompt = app.bulk_prompt();
     let edit_block = match &app.mode() {
-        app::Mode::Edit => ratatui::widgets::Paragraph::new(ui_state.input.value())
-            .style(ratatui::style::Style::default().fg(FOCUS_COLOR).bg(BG_COLOR))
-            .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::ALL)),
+        app::Mode::Edit if app.edit_target() == app::EditTarget::Capture => {
+            render_hint("[ENT]: Add task  [TAB/S-TAB]: Complete  [ESC]: Cancel")
+        }
+        app::Mode::Edit => {
+            let title = match app.edit_target() {
+                app::EditTarget::Task | app::EditTarget::Capture => "",
+                app::EditTarget::Field(field) => field.name(),
+            };
+            ratatui::widgets::Paragraph::new(ui_state.input.value())
+                .style(ratatui::style::Style::default().fg(FOCUS_COLOR).bg(BG_COLOR))
+                .block(
+                    ratatui::widgets::Block::default()
+                        .title(title)
+                        .borders(ratatui::widgets::Borders::ALL),
+                )
+        }
         app::Mode::Confirm(action) => {
+            let overwrite_prompt;
             let action_str = match action {
+                app::ConfirmedAction::Save if !app.modified_sources().is_empty() => {
+                    overwrite_prompt = format!(
+                        "Overwrite changes made by another program to {}",
+                        source_list(app, app.modified_sources())
+                    );
+                    &overwrite_prompt
+                }
+                app::ConfirmedAction::Save if app.sources().len() > 1 => "Save files",
                 app::ConfirmedAction::Save => "Save file",
                 app::ConfirmedAction::Sort => "Sort tasks",
+                app::ConfirmedAction::Delete => "Delete task",
+                app::ConfirmedAction::Bulk => &bulk_prompt,
             };
-            ratatui::widgets::Paragraph::new(format!("{}? [Y/n]", action_str))
+            let choice = if app.confirm_by_default() {
+                "[Y/n]"
+            } else {
+                "[y/N]"
+            };
+            ratatui::widgets::Paragraph::new(format!("{}? {}", action_str, choice))
                 .style(ratatui::style::Style::default().fg(FOCUS_COLOR).bg(BG_COLOR))
                 .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::ALL))
         }
-        _ => {
-            ratatui::widgets::Paragraph::new(
-                "", //format!("{:.2}", app.frame_time*1000.0f64)
-                   //"hjkl: navigate  <ent>: begin/save edit  <esc>: cancel edit  q: quit  s: save  S:sort",
+        app::Mode::Report => render_hint("[ESC/R]: Exit report"),
+        app::Mode::TimeReport => render_hint(
+            "[d/w/m]: Day/week/month  [h/l]: Previous/next period  [ESC/Y]: Exit time report",
+        ),
+        app::Mode::Priority => {
+            render_hint("Priority? [A-Z]: Set priority  [SPC/BS]: Clear priority  [ESC]: Cancel")
+        }
+        app::Mode::DatePicker => render_hint(
+            "[h/l]: Day  [j/k]: Week  [H/L]: Month  [t]: Today  [ENT]: Set due date  [BS]: Clear due date  [ESC]: Cancel",
+        ),
+        app::Mode::Pick(_) => {
+            render_hint("[j/k]: Select  [SPC/ENT]: Add/remove from task  [ESC]: Done")
+        }
+        app::Mode::Bulk(_) => render_hint("[j/k]: Select  [ENT]: Choose  [ESC]: Cancel"),
+        app::Mode::Trash => render_hint("[j/k]: Select  [ENT]: Restore task  [ESC]: Done"),
+        app::Mode::Link => {
+            render_hint("[j/k]: Select  [SPC/ENT]: Add/remove dependency  [ESC]: Done")
+        }
+        app::Mode::Dependencies => render_hint("[ESC/G]: Close"),
+        app::Mode::Parent => render_hint("[j/k]: Select  [ENT]: Set/clear parent  [ESC]: Cancel"),
+        _ if !app.error_msg().is_empty() => ratatui::widgets::Paragraph::new(app.error_msg())
+            .style(
+                ratatui::style::Style::default()
+                    .fg(ERROR_COLOR)
+                    .bg(BG_COLOR),
             )
+            .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::ALL)),
+        app::Mode::Detail => {
+            render_hint("[j/k]: Select field  [e/ENT]: Edit field  [ESC/f]: Exit detail")
+        }
+        _ => {
+            let counts = app.task_counts();
+            let mut spans = vec![
+                ratatui::text::Span::raw(format!("open: {}  done: {}  ", counts.open, counts.done)),
+                ratatui::text::Span::styled(
+                    format!("overdue: {}", counts.overdue),
+                    if counts.overdue > 0 {
+                        ratatui::style::Style::default().fg(OVERDUE_COLOR)
+                    } else {
+                        ratatui::style::Style::default()
+                    },
+                ),
+            ];
+            if !app.modified_sources().is_empty() {
+                spans.push(ratatui::text::Span::styled(
+                    format!("  modified: {}", source_list(app, app.modified_sources())),
+                    ratatui::style::Style::default().fg(ERROR_COLOR),
+                ));
+            }
+            if app.marked_count() > 0 {
+                spans.push(ratatui::text::Span::styled(
+                    format!("  marked: {}", app.marked_count()),
+                    ratatui::style::Style::default().fg(SELECTION_COLOR),
+                ));
+            }
+            if let Some((task, phase, remaining)) = app.pomodoro() {
+                let seconds = remaining.num_seconds();
+                let phase = match phase {
+                    ::app::pomodoro::Phase::Work => "work",
+                    ::app::pomodoro::Phase::Break => "break",
+                };
+                spans.push(ratatui::text::Span::styled(
+                    format!(
+                        "  {} {:02}:{:02} {}",
+                        phase,
+                        seconds / 60,
+                        seconds % 60,
+                        task.description()
+                    ),
+                    ratatui::style::Style::default().fg(SELECTION_COLOR),
+                ));
+            }
+            ratatui::widgets::Paragraph::new(ratatui::text::Line::from(spans))
             .style(ratatui::style::Style::default().fg(UNFOCUS_COLOR).bg(BG_COLOR))
             .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::ALL))
         }
     };
     frame.render_widget(edit_block, chunks[2]);
 
+    //
+    // Popups drawn over the body
+    //
+    match app.mode() {
+        app::Mode::DatePicker => draw_date_picker(
+            frame,
+            app.picker_date(),
+            app.today(),
+            app.week_start(),
+            chunks[1],
+        ),
+        app::Mode::Pick(kind) => draw_pick_list(frame, app, kind, ui_state, chunks[1]),
+        app::Mode::Bulk(command) => draw_bulk_list(frame, app, command, ui_state, chunks[1]),
+        app::Mode::Link => {
+            let items: Vec<String> = app
+                .pick_list()
+                .items()
+                .iter()
+                .enumerate()
+                .map(|(pos, task)| {
+                    let mark = if app.is_linked(pos) { "[x]" } else { "[ ]" };
+                    format!("{} {}", mark, task)
+                })
+                .collect();
+            ui_state.pick_list_state.select(app.pick_list().selection());
+            draw_popup_list(
+                frame,
+                "depends on",
+                &items,
+                &mut ui_state.pick_list_state,
+                chunks[1],
+            );
+        }
+        app::Mode::Dependencies => draw_dependencies(frame, app, chunks[1]),
+        app::Mode::Parent => {
+            let items: Vec<String> = app
+                .pick_list()
+                .items()
+                .iter()
+                .enumerate()
+                .map(|(pos, task)| {
+                    let mark = if app.is_parent(pos) { "[x]" } else { "[ ]" };
+                    format!("{} {}", mark, task)
+                })
+                .collect();
+            ui_state.pick_list_state.select(app.pick_list().selection());
+            draw_popup_list(
+                frame,
+                "parent",
+                &items,
+                &mut ui_state.pick_list_state,
+                chunks[1],
+            );
+        }
+        app::Mode::Trash => {
+            ui_state.pick_list_state.select(app.pick_list().selection());
+            draw_popup_list(
+                frame,
+                "trash",
+                app.pick_list().items(),
+                &mut ui_state.pick_list_state,
+                chunks[1],
+            );
+        }
+        _ => {}
+    }
+
     if app.mode() == app::Mode::Edit {
-        let width = chunks[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
+        // Quick add edits in a popup over the body rather than the edit line
+        let edit_area = if app.edit_target() == app::EditTarget::Capture {
+            let popup = centered_rect(chunks[1].width * 2 / 3, 3, chunks[1]);
+            let title = match app.capture_default() {
+                "" => "quick add".to_string(),
+                defaults => format!("quick add ({})", defaults),
+            };
+            frame.render_widget(ratatui::widgets::Clear, popup);
+            frame.render_widget(
+                ratatui::widgets::Paragraph::new(ui_state.input.value())
+                    .style(
+                        ratatui::style::Style::default()
+                            .fg(FOCUS_COLOR)
+                            .bg(BG_COLOR),
+                    )
+                    .block(
+                        ratatui::widgets::Block::default()
+                            .title(title)
+                            .borders(ratatui::widgets::Borders::ALL),
+                    ),
+                popup,
+            );
+            popup
+        } else {
+            chunks[2]
+        };
+
+        let width = edit_area.width.max(3) - 3; // keep 2 for borders and 1 for cursor
         let scroll = ui_state.input.visual_scroll(width as usize);
+        if let Some(completion) = &ui_state.completion {
+            draw_completion(frame, completion, scroll, edit_area);
+        }
         // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
         frame.set_cursor(
             // Put cursor past the end of the input text
-            chunks[2].x + ((ui_state.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+            edit_area.x + ((ui_state.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
             // Move one line down, from the border to the input line
-            chunks[2].y + 1,
+            edit_area.y + 1,
         )
     }
 }
 
+/// Area of the given size centered within `area`, clipped to fit
+fn centered_rect(width: u16, height: u16, area: ratatui::layout::Rect) -> ratatui::layout::Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    ratatui::layout::Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Draw a month calendar popup with the picked date highlighted
+fn draw_date_picker<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    picked: chrono::NaiveDate,
+    today: chrono::NaiveDate,
+    week_start: chrono::Weekday,
+    area: ratatui::layout::Rect,
+) {
+    use chrono::Datelike;
+
+    let first = picked.with_day(1).unwrap();
+
+    let mut weekday = week_start;
+    let mut header = Vec::new();
+    for _ in 0..7 {
+        header.push(weekday.to_string()[..2].to_string());
+        weekday = weekday.succ();
+    }
+    let mut lines = vec![ratatui::text::Line::from(ratatui::text::Span::styled(
+        header.join(" "),
+        ratatui::style::Style::default().fg(UNFOCUS_COLOR),
+    ))];
+    let mut day = todo_txt::date::start_of_week(first, week_start);
+    while day.month() == picked.month() || day < first {
+        let mut spans = Vec::new();
+        for weekday in 0..7 {
+            let text = if day.month() == picked.month() {
+                format!("{:2}", day.day())
+            } else {
+                "  ".to_string()
+            };
+            let mut style = ratatui::style::Style::default().fg(FOCUS_COLOR);
+            if day == today {
+                style = style.add_modifier(ratatui::style::Modifier::UNDERLINED);
+            }
+            if day == picked {
+                style = style.fg(BG_COLOR).bg(SELECTION_COLOR);
+            }
+            spans.push(ratatui::text::Span::styled(text, style));
+            if weekday < 6 {
+                spans.push(ratatui::text::Span::raw(" "));
+            }
+            day += chrono::Duration::days(1);
+        }
+        lines.push(ratatui::text::Line::from(spans));
+    }
+
+    let popup = centered_rect(22, lines.len() as u16 + 2, area);
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_widget(
+        ratatui::widgets::Paragraph::new(lines).block(
+            ratatui::widgets::Block::default()
+                .title(picked.format("due %Y-%m-%d").to_string())
+                .borders(ratatui::widgets::Borders::ALL)
+                .style(
+                    ratatui::style::Style::default()
+                        .fg(FOCUS_COLOR)
+                        .bg(BG_COLOR),
+                ),
+        ),
+        popup,
+    );
+}
+
+/// Draw a popup listing existing contexts or projects, marking those present on
+/// the selected task
+fn draw_pick_list<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    app: &app::App,
+    kind: app::PickKind,
+    ui_state: &mut state::State,
+    area: ratatui::layout::Rect,
+) {
+    let (title, prefix) = match kind {
+        app::PickKind::Context => ("contexts", "@"),
+        app::PickKind::Project => ("projects", "+"),
+    };
+    let items: Vec<String> = app
+        .pick_list()
+        .items()
+        .iter()
+        .map(|name| {
+            let mark = if app.is_picked(kind, name) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            format!("{} {}{}", mark, prefix, name)
+        })
+        .collect();
+    let width = items
+        .iter()
+        .map(|item| item.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+
+    let popup = centered_rect(width.max(20), items.len() as u16 + 2, area);
+    ui_state.pick_list_state.select(app.pick_list().selection());
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_stateful_widget(
+        render_list(title, &items, true),
+        popup,
+        &mut ui_state.pick_list_state,
+    );
+}
+
+/// Draw a popup menu of bulk commands, or of the choices for the chosen command
+fn draw_bulk_list<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    app: &app::App,
+    command: Option<::app::bulk::BulkCommand>,
+    ui_state: &mut state::State,
+    area: ratatui::layout::Rect,
+) {
+    let (title, items, list_state) = match command {
+        None => {
+            let items: Vec<String> = app
+                .bulk_list()
+                .items()
+                .iter()
+                .map(|command| command.name().to_string())
+                .collect();
+            ui_state.bulk_list_state.select(app.bulk_list().selection());
+            (
+                format!("{} marked", app.marked_count()),
+                items,
+                &mut ui_state.bulk_list_state,
+            )
+        }
+        Some(command) => {
+            ui_state.pick_list_state.select(app.pick_list().selection());
+            (
+                command.name().to_string(),
+                app.pick_list().items().clone(),
+                &mut ui_state.pick_list_state,
+            )
+        }
+    };
+    draw_popup_list(frame, &title, &items, list_state, area);
+}
+
+/// Draw a list centered over `area`, sized to fit its items where possible
+fn draw_popup_list<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    title: &str,
+    items: &[String],
+    list_state: &mut ratatui::widgets::ListState,
+    area: ratatui::layout::Rect,
+) {
+    let width = items
+        .iter()
+        .map(|item| item.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+
+    let popup = centered_rect(width.max(20), items.len() as u16 + 2, area);
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_stateful_widget(render_list(title, items, true), popup, list_state);
+}
+
+/// Draw the tree of tasks the selected task depends on, and the tasks which
+/// depend on it.  Completed tasks are checked.
+fn draw_dependencies<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    app: &app::App,
+    area: ratatui::layout::Rect,
+) {
+    let line = |depth: usize, idx: usize| {
+        let task = &app.tasks()[idx];
+        let mark = if task.completed { "[x]" } else { "[ ]" };
+        format!("{}{} {}", "  ".repeat(depth), mark, task)
+    };
+
+    let mut items = vec!["blocked by:".to_string()];
+    let tree = app.dependency_tree();
+    if tree.is_empty() {
+        items.push("  -".to_string());
+    }
+    items.extend(tree.into_iter().map(|(depth, idx)| line(depth, idx)));
+    items.push("blocks:".to_string());
+    let dependents = app.dependents();
+    if dependents.is_empty() {
+        items.push("  -".to_string());
+    }
+    items.extend(dependents.into_iter().map(|idx| line(1, idx)));
+
+    let title = app.dependencies_task().to_string();
+    draw_popup_list(
+        frame,
+        &title,
+        &items,
+        &mut ratatui::widgets::ListState::default(),
+        area,
+    );
+}
+
+/// Draw the completion menu just above the edit line, aligned with the start of
+/// the word being completed
+fn draw_completion<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    completion: &state::Completion,
+    scroll: usize,
+    edit_area: ratatui::layout::Rect,
+) {
+    let width = completion
+        .candidates
+        .iter()
+        .map(|candidate| candidate.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let height = (completion.candidates.len() as u16 + 2).min(edit_area.y);
+    let x = edit_area.x + 1 + completion.word_start.saturating_sub(scroll) as u16;
+    let popup = ratatui::layout::Rect {
+        x: x.min(edit_area.right().saturating_sub(width)),
+        y: edit_area.y - height,
+        width: width.min(edit_area.width),
+        height,
+    };
+
+    let mut list_state = ratatui::widgets::ListState::default();
+    list_state.select(completion.selected);
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_stateful_widget(
+        render_list("", &completion.candidates, true),
+        popup,
+        &mut list_state,
+    );
+}
+
+/// Draw completion history, task ages, project burndowns and top contexts
+fn draw_report<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    report: &Report,
+    area: ratatui::layout::Rect,
+) {
+    let rows = ratatui::layout::Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints(
+            [
+                ratatui::layout::Constraint::Percentage(40), // completion history
+                ratatui::layout::Constraint::Percentage(60), // burndowns, contexts
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let top_chunks = ratatui::layout::Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints(
+            [
+                ratatui::layout::Constraint::Percentage(50), // daily
+                ratatui::layout::Constraint::Percentage(50), // weekly
+            ]
+            .as_ref(),
+        )
+        .split(rows[0]);
+    let bottom_chunks = ratatui::layout::Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints(
+            [
+                ratatui::layout::Constraint::Percentage(60), // burndowns
+                ratatui::layout::Constraint::Percentage(40), // summary, contexts
+            ]
+            .as_ref(),
+        )
+        .split(rows[1]);
+    let side_chunks = ratatui::layout::Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints(
+            [
+                ratatui::layout::Constraint::Length(5), // summary
+                ratatui::layout::Constraint::Min(3),    // top contexts
+            ]
+            .as_ref(),
+        )
+        .split(bottom_chunks[1]);
+
+    let block = |title: String| {
+        ratatui::widgets::Block::default()
+            .title(title)
+            .borders(ratatui::widgets::Borders::ALL)
+            .style(ratatui::style::Style::default().fg(FOCUS_COLOR).bg(BG_COLOR))
+    };
+
+    // Completed tasks per day
+    frame.render_widget(
+        ratatui::widgets::Sparkline::default()
+            .block(block(format!(
+                "completed per day (last {} days)",
+                REPORT_DAYS
+            )))
+            .style(ratatui::style::Style::default().fg(SELECTION_COLOR))
+            .data(&report.completed_per_day),
+        top_chunks[0],
+    );
+
+    // Completed tasks per week
+    let weeks: Vec<(String, u64)> = report
+        .completed_per_week
+        .iter()
+        .map(|(week_start, count)| (week_start.format("%V").to_string(), *count))
+        .collect();
+    let weeks: Vec<(&str, u64)> = weeks.iter().map(|(l, c)| (l.as_str(), *c)).collect();
+    let bar_width = (top_chunks[1].width.saturating_sub(2) / weeks.len().max(1) as u16)
+        .saturating_sub(1)
+        .max(1);
+    frame.render_widget(
+        ratatui::widgets::BarChart::default()
+            .block(block("completed per week (by week number)".to_string()))
+            .bar_width(bar_width)
+            .bar_style(ratatui::style::Style::default().fg(SELECTION_COLOR))
+            .data(weeks.as_slice()),
+        top_chunks[1],
+    );
+
+    // Open tasks per project at the end of each week, one row per project
+    let burndown_block = block(format!(
+        "open tasks per project (last {} weeks)",
+        REPORT_WEEKS
+    ));
+    let burndown_area = burndown_block.inner(bottom_chunks[0]);
+    frame.render_widget(burndown_block, bottom_chunks[0]);
+    let label_width = report
+        .burndowns
+        .iter()
+        .map(|burndown| burndown.project.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 1;
+    for (row, burndown) in report
+        .burndowns
+        .iter()
+        .take(burndown_area.height as usize)
+        .enumerate()
+    {
+        let row_area = ratatui::layout::Rect {
+            y: burndown_area.y + row as u16,
+            height: 1,
+            ..burndown_area
+        };
+        let row_chunks = ratatui::layout::Layout::default()
+            .direction(ratatui::layout::Direction::Horizontal)
+            .constraints(
+                [
+                    ratatui::layout::Constraint::Length(label_width),
+                    ratatui::layout::Constraint::Length(REPORT_WEEKS as u16),
+                    ratatui::layout::Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(row_area);
+        frame.render_widget(
+            ratatui::widgets::Paragraph::new(burndown.project.as_str()),
+            row_chunks[0],
+        );
+        frame.render_widget(
+            ratatui::widgets::Sparkline::default()
+                .style(ratatui::style::Style::default().fg(SELECTION_COLOR))
+                .max(burndown.open.iter().copied().max().unwrap_or(0))
+                .data(&burndown.open),
+            row_chunks[1],
+        );
+        frame.render_widget(
+            ratatui::widgets::Paragraph::new(format!(
+                " {}",
+                burndown.open.last().copied().unwrap_or(0)
+            )),
+            row_chunks[2],
+        );
+    }
+
+    // Summary
+    let last_week: u64 = report.completed_per_day.iter().rev().take(7).sum();
+    let last_month: u64 = report.completed_per_day.iter().sum();
+    let summary = vec![
+        ratatui::text::Line::from(match report.average_open_age {
+            Some(age) => format!("average age of open tasks: {:.1} days", age),
+            None => "average age of open tasks: -".to_string(),
+        }),
+        ratatui::text::Line::from(format!("completed in last 7 days: {}", last_week)),
+        ratatui::text::Line::from(format!(
+            "completed in last {} days: {}",
+            REPORT_DAYS, last_month
+        )),
+    ];
+    frame.render_widget(
+        ratatui::widgets::Paragraph::new(summary)
+            .block(block(format!("summary ({})", report.today))),
+        side_chunks[0],
+    );
+
+    // Contexts with the most open tasks.  Horizontal bars do not show labels,
+    // so include the context name in the displayed value
+    let contexts: Vec<ratatui::widgets::Bar> = report
+        .top_contexts
+        .iter()
+        .map(|(context, count)| {
+            ratatui::widgets::Bar::default()
+                .value(*count)
+                .text_value(format!("{} {}", context, count))
+        })
+        .collect();
+    frame.render_widget(
+        ratatui::widgets::BarChart::default()
+            .block(block("top contexts (open tasks)".to_string()))
+            .direction(ratatui::layout::Direction::Horizontal)
+            .bar_width(1)
+            .bar_gap(0)
+            .bar_style(ratatui::style::Style::default().fg(SELECTION_COLOR))
+            .value_style(
+                ratatui::style::Style::default()
+                    .fg(ratatui::style::Color::Black)
+                    .bg(SELECTION_COLOR),
+            )
+            .data(ratatui::widgets::BarGroup::default().bars(&contexts)),
+        side_chunks[1],
+    );
+}
+
+/// Draw time spent per project and context as horizontal bars
+fn draw_time_report<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    time_report: &TimeReport,
+    area: ratatui::layout::Rect,
+) {
+    let chunks = ratatui::layout::Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints(
+            [
+                ratatui::layout::Constraint::Percentage(50), // projects
+                ratatui::layout::Constraint::Percentage(50), // contexts
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let range = if time_report.from == time_report.to {
+        time_report.from.to_string()
+    } else {
+        format!("{} to {}", time_report.from, time_report.to)
+    };
+    let total = todo_txt::date::format_duration(time_report.total);
+    let block = |title: String| {
+        ratatui::widgets::Block::default()
+            .title(title)
+            .borders(ratatui::widgets::Borders::ALL)
+            .style(ratatui::style::Style::default().fg(FOCUS_COLOR).bg(BG_COLOR))
+    };
+    for (chunk, kind, spent) in [
+        (chunks[0], "project", &time_report.projects),
+        (chunks[1], "context", &time_report.contexts),
+    ] {
+        // Horizontal bars do not show labels, so include the name in the
+        // displayed value
+        let bars: Vec<ratatui::widgets::Bar> = spent
+            .iter()
+            .map(|(name, duration)| {
+                ratatui::widgets::Bar::default()
+                    .value(duration.num_minutes().max(0) as u64)
+                    .text_value(format!(
+                        "{} {}",
+                        name,
+                        todo_txt::date::format_duration(*duration)
+                    ))
+            })
+            .collect();
+        frame.render_widget(
+            ratatui::widgets::BarChart::default()
+                .block(block(format!(
+                    "time per {} ({}, total {})",
+                    kind, range, total
+                )))
+                .direction(ratatui::layout::Direction::Horizontal)
+                .bar_width(1)
+                .bar_gap(0)
+                .bar_style(ratatui::style::Style::default().fg(SELECTION_COLOR))
+                .value_style(
+                    ratatui::style::Style::default()
+                        .fg(ratatui::style::Color::Black)
+                        .bg(SELECTION_COLOR),
+                )
+                .data(ratatui::widgets::BarGroup::default().bars(&bars)),
+            chunk,
+        );
+    }
+}
+
+/// Append open and completed task counts to a list label, right aligned within a
+/// pane of the given width.  Optionally include a bar showing the fraction of
+/// completed tasks.  The bar, then the counts, are dropped if the pane is too
+/// narrow to fit them.
+fn with_counts(
+    label: &str,
+    counts: todo_txt::tasks::TaskCounts,
+    progress_bar: bool,
+    width: u16,
+) -> String {
+    const BAR_WIDTH: usize = 5;
+
+    let width = (width as usize).saturating_sub(2); // borders
+    let counts_str = format!("{} ✓{}", counts.open, counts.done);
+    let mut suffixes = Vec::new();
+    if progress_bar && counts.total() > 0 {
+        let filled = (BAR_WIDTH * counts.done + counts.total() / 2) / counts.total();
+        suffixes.push(format!(
+            "{}{} {}",
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH - filled),
+            counts_str
+        ));
+    }
+    suffixes.push(counts_str);
+
+    let label_width = label.chars().count();
+    for suffix in suffixes {
+        let suffix_width = suffix.chars().count();
+        if label_width + suffix_width < width {
+            return format!(
+                "{}{:pad$}{}",
+                label,
+                "",
+                suffix,
+                pad = width - label_width - suffix_width
+            );
+        }
+    }
+    label.to_string()
+}
+
+/// Quoted names of the given sources, separated by commas
+fn source_list(app: &app::App, sources: &[usize]) -> String {
+    sources
+        .iter()
+        .map(|&source| format!("'{}'", app.source_name(source)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Footer paragraph showing key bindings for the current mode
+fn render_hint(hint: &str) -> ratatui::widgets::Paragraph<'_> {
+    ratatui::widgets::Paragraph::new(hint)
+        .style(
+            ratatui::style::Style::default()
+                .fg(UNFOCUS_COLOR)
+                .bg(BG_COLOR),
+        )
+        .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::ALL))
+}
+
+/// Range of the items of a list of `len` items to show in `height` rows,
+/// scrolling the list's `state` just enough to keep its selection in view
+fn visible_window(
+    state: &mut ratatui::widgets::ListState,
+    len: usize,
+    height: usize,
+) -> std::ops::Range<usize> {
+    let mut offset = state.offset().min(len.saturating_sub(height));
+    if let Some(selected) = state.selected() {
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + height {
+            offset = selected + 1 - height.max(1);
+        }
+    }
+    *state.offset_mut() = offset;
+    offset..(offset + height).min(len)
+}
+
+/// Draw a list of names in a side pane, formatting only the visible ones with
+/// `format`
+fn draw_side_pane<B: ratatui::backend::Backend>(
+    frame: &mut ratatui::Frame<B>,
+    title: &str,
+    names: &[String],
+    format: impl Fn(&String) -> String,
+    is_focus: bool,
+    area: ratatui::layout::Rect,
+    state: &mut ratatui::widgets::ListState,
+) {
+    let window = visible_window(state, names.len(), area.height.saturating_sub(2) as usize);
+    let mut window_state = ratatui::widgets::ListState::default()
+        .with_selected(state.selected().map(|selected| selected - window.start));
+    let items: Vec<String> = names[window].iter().map(format).collect();
+    frame.render_stateful_widget(
+        render_list(title, &items, is_focus),
+        area,
+        &mut window_state,
+    );
+}
+
 fn render_list<'a>(
     title: &'a str,
     item_strings: &'a [String],
@@ -182,7 +1147,15 @@ fn render_list<'a>(
         .iter()
         .map(|s| ratatui::widgets::ListItem::new(ratatui::text::Line::from(s.as_str())))
         .collect();
+    render_items(title, items, is_focus)
+}
 
+/// See [render_list], for items which are already styled
+fn render_items<'a>(
+    title: &'a str,
+    items: Vec<ratatui::widgets::ListItem<'a>>,
+    is_focus: bool,
+) -> ratatui::widgets::List<'a> {
     // Create a List from all list items and highlight the currently selected one
     ratatui::widgets::List::new(items)
         .block(
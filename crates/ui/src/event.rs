@@ -0,0 +1,155 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Event driving the main loop: either user input or an event for the app
+pub enum Event {
+    Input(crossterm::event::Event),
+    App(app::AppEvent),
+}
+
+/// Source of the events driving the main loop, so the loop can be driven by a
+/// script as well as by the terminal
+pub trait EventSource {
+    /// Wait for the next event
+    fn wait(&mut self) -> std::io::Result<Event>;
+}
+
+/// Source of events for the main loop.  Terminal input is polled on the
+/// calling thread (so an external editor can take over the terminal), ticks
+/// are generated every `tick_rate`, and background threads inject app events
+/// through the channel returned by [Events::sender].
+pub struct Events {
+    tick_rate: Duration,
+    last_tick: Instant,
+    sender: mpsc::Sender<app::AppEvent>,
+    receiver: mpsc::Receiver<app::AppEvent>,
+}
+
+impl Events {
+    pub fn new(tick_rate: Duration) -> Events {
+        let (sender, receiver) = mpsc::channel();
+        Events {
+            tick_rate,
+            last_tick: Instant::now(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Channel for background sources to send events to the app through
+    pub fn sender(&self) -> mpsc::Sender<app::AppEvent> {
+        self.sender.clone()
+    }
+}
+
+impl EventSource for Events {
+    /// Wait for the next event.  Events from background sources come first,
+    /// then terminal input, and a tick once `tick_rate` has passed since the
+    /// previous one.
+    fn wait(&mut self) -> std::io::Result<Event> {
+        loop {
+            if let Ok(event) = self.receiver.try_recv() {
+                return Ok(Event::App(event));
+            }
+
+            let timeout = self.tick_rate.saturating_sub(self.last_tick.elapsed());
+            // Poll in short slices so background events are not held up until
+            // the next tick
+            if crossterm::event::poll(timeout.min(POLL_SLICE))? {
+                return Ok(Event::Input(crossterm::event::read()?));
+            }
+
+            if self.last_tick.elapsed() >= self.tick_rate {
+                self.last_tick = Instant::now();
+                return Ok(Event::App(app::AppEvent::Tick));
+            }
+        }
+    }
+}
+
+/// Events given up front, eg, key presses to drive the main loop in tests.
+/// Once all events are taken, waiting fails with
+/// [std::io::ErrorKind::UnexpectedEof], ending the loop.
+pub struct ScriptedEvents {
+    events: std::collections::VecDeque<Event>,
+}
+
+impl ScriptedEvents {
+    pub fn new(events: Vec<Event>) -> ScriptedEvents {
+        ScriptedEvents {
+            events: events.into(),
+        }
+    }
+
+    /// Script of the given key presses
+    pub fn keys(codes: &[crossterm::event::KeyCode]) -> ScriptedEvents {
+        ScriptedEvents::new(codes.iter().map(|code| key(*code)).collect())
+    }
+
+    /// Script of a key press for each character of `text`
+    pub fn typed(text: &str) -> ScriptedEvents {
+        ScriptedEvents::new(
+            text.chars()
+                .map(|c| key(crossterm::event::KeyCode::Char(c)))
+                .collect(),
+        )
+    }
+
+    /// Add events to the end of the script
+    pub fn push(&mut self, events: ScriptedEvents) {
+        self.events.extend(events.events);
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn wait(&mut self) -> std::io::Result<Event> {
+        self.events
+            .pop_front()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "end of script"))
+    }
+}
+
+/// Press of a key without modifiers
+pub fn key(code: crossterm::event::KeyCode) -> Event {
+    Event::Input(crossterm::event::Event::Key(
+        crossterm::event::KeyEvent::new(code, crossterm::event::KeyModifiers::NONE),
+    ))
+}
+
+/// Longest wait for terminal input before checking for background events
+const POLL_SLICE: Duration = Duration::from_millis(100);
+
+/// Watch the modification times of the given files on a background thread,
+/// sending [app::AppEvent::SourceModified] with the index of the file when one
+/// changes.  The thread ends when a change can no longer be sent, ie, once
+/// the receiving [Events] is dropped.
+pub fn watch_sources(
+    paths: Vec<String>,
+    sender: mpsc::Sender<app::AppEvent>,
+    interval: Duration,
+) -> std::thread::JoinHandle<()> {
+    let modified = |path: &String| -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    };
+    std::thread::spawn(move || {
+        let mut last_modified: Vec<Option<SystemTime>> = paths.iter().map(modified).collect();
+        loop {
+            std::thread::sleep(interval);
+            for (source, path) in paths.iter().enumerate() {
+                let current = modified(path);
+                if current == last_modified[source] {
+                    continue;
+                }
+                last_modified[source] = current;
+                if let Some(time) = current {
+                    let event = app::AppEvent::SourceModified(source, time);
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    })
+}
@@ -1,3 +1,4 @@
 pub mod draw;
+pub mod event;
 pub mod state;
 pub mod terminal;
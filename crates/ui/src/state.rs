@@ -4,8 +4,23 @@ pub struct State {
     pub context_list_state: ratatui::widgets::ListState,
     pub project_list_state: ratatui::widgets::ListState,
     pub priority_list_state: ratatui::widgets::ListState,
+    pub detail_list_state: ratatui::widgets::ListState,
+    pub pick_list_state: ratatui::widgets::ListState,
+    pub bulk_list_state: ratatui::widgets::ListState,
 
     pub input: tui_input::Input,
+    pub completion: Option<Completion>,
+}
+
+/// Completion menu shown while typing a context, project or tag key in edit mode
+#[derive(Default)]
+pub struct Completion {
+    /// Candidates for the word being typed, best match first
+    pub candidates: Vec<String>,
+    /// Candidate currently inserted into the input, when cycling with Tab
+    pub selected: Option<usize>,
+    /// Char index of the start of the word being completed
+    pub word_start: usize,
 }
 
 impl State {
@@ -15,7 +30,66 @@ impl State {
             context_list_state: ratatui::widgets::ListState::default(),
             project_list_state: ratatui::widgets::ListState::default(),
             priority_list_state: ratatui::widgets::ListState::default(),
+            detail_list_state: ratatui::widgets::ListState::default(),
+            pick_list_state: ratatui::widgets::ListState::default(),
+            bulk_list_state: ratatui::widgets::ListState::default(),
             input: tui_input::Input::new("".to_string()),
+            completion: None,
         }
     }
+
+    /// Replace the input line, closing any completion menu
+    pub fn set_input(&mut self, value: String) {
+        self.input = tui_input::Input::new(value);
+        self.completion = None;
+    }
+
+    /// Recompute completion candidates for the word ending at the cursor
+    pub fn update_completion(&mut self, app: &app::App) {
+        let value: Vec<char> = self.input.value().chars().collect();
+        let cursor = self.input.cursor().min(value.len());
+        let word_start = value[..cursor]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |pos| pos + 1);
+        let word: String = value[word_start..cursor].iter().collect();
+
+        let candidates = app.completions(&word);
+        self.completion = if candidates.is_empty() {
+            None
+        } else {
+            Some(Completion {
+                candidates,
+                selected: None,
+                word_start,
+            })
+        };
+    }
+
+    /// Replace the word being completed with the next (or previous) candidate
+    pub fn cycle_completion(&mut self, forward: bool) {
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+        let num_candidates = completion.candidates.len();
+        let selected = match (completion.selected, forward) {
+            (None, true) => 0,
+            (None, false) => num_candidates - 1,
+            (Some(i), true) => (i + 1) % num_candidates,
+            (Some(i), false) => (i + num_candidates - 1) % num_candidates,
+        };
+        completion.selected = Some(selected);
+
+        let candidate = &completion.candidates[selected];
+        let value: Vec<char> = self.input.value().chars().collect();
+        let cursor = self.input.cursor().min(value.len());
+        let new_value: String = value[..completion.word_start]
+            .iter()
+            .copied()
+            .chain(candidate.chars())
+            .chain(value[cursor..].iter().copied())
+            .collect();
+        let new_cursor = completion.word_start + candidate.chars().count();
+        self.input = tui_input::Input::new(new_value).with_cursor(new_cursor);
+    }
 }
@@ -1,4 +1,6 @@
 use crate::draw;
+use crate::event;
+use crate::event::EventSource;
 use crate::state;
 
 use tui_input::backend::crossterm::EventHandler;
@@ -17,14 +19,86 @@ Normal mode:
   [k/UP]:    Move selection down one item in current pane 
   [l/DOWN]:  Move focus one pane to right
   [e/ENT]:   Enter edit mode on current task selection
+  [a]:       Quickly add a task with the capture defaults
   [x]:       Toggle visibility of all completed tasks
+  [I]:       Toggle inbox view of tasks without a context or project
+  [B]:       Toggle visibility of tasks blocked by open dependencies
+  [O]:       Toggle outline view of subtasks beneath their parents
   [X]:       Toggle completion of current task
+  [z]:       Collapse/expand selected nested context, project or parent task
+  [i]:       Toggle detail pane for current task
+  [f]:       Enter detail mode to edit fields of current task
+  [P]:       Set priority of current task
+  [+/-]:     Raise/lower priority of current task
+  [t]:       Choose due date of current task
+  [c/p]:     Add/remove existing contexts/projects on current task
+  [L]:       Add/remove tasks the current task depends on
+  [G]:       Show dependency tree of current task
+  [>]:       Choose parent of current task
+  [m]:       Mark/unmark current task
+  [M]:       Mark all listed tasks (or unmark if all are marked)
+  [*]:       Invert marks of all listed tasks
+  [b]:       Apply a command to all marked tasks
+  [u]:       Undo last modification
+  [D]:       Delete current task (after confirmation)
+  [T]:       Show deleted tasks to restore them
+  [E]:       Edit current task in $EDITOR (add tasks if [new] is selected)
+  [V]:       Edit all listed tasks in $EDITOR
   [H/SPC]:   Enter help mode display 
+  [R]:       Enter report mode display
+  [r]:       Start/stop timing current task
+  [o]:       Start/stop a pomodoro on current task
+  [Y]:       Enter time report mode display
 Edit mode:
   [ESC]:     Exit edit mode without saving any modifications
-  [ENT]:     Exit edit mode and save modifications
+  [ENT]:     Exit edit mode and save modifications (an empty task is deleted)
+  [TAB/S-TAB]: Cycle through completions of @context, +project or tag key
+Detail mode:
+  [j/k]:     Move selection between fields of current task
+  [e/ENT]:   Edit selected field (or toggle completion)
+  [ESC/f]:   Exit detail mode
+Priority mode:
+  [A-Z]:     Set priority of current task
+  [SPC/BS]:  Clear priority of current task
+  [ESC]:     Cancel
+Date picker mode:
+  [h/l]:     Move selection one day back/forward
+  [j/k]:     Move selection one week forward/back
+  [H/L]:     Move selection one month back/forward
+  [t]:       Move selection to today
+  [ENT]:     Set due date of current task to selection
+  [BS]:      Clear due date of current task
+  [ESC]:     Cancel
+Context/project pick mode:
+  [j/k]:     Move selection down/up
+  [SPC/ENT]: Add/remove selection on current task
+  [ESC]:     Exit pick mode
+Link mode:
+  [j/k]:     Move selection down/up
+  [SPC/ENT]: Add/remove dependency of current task on selection
+  [ESC]:     Exit link mode
+Parent mode:
+  [j/k]:     Move selection down/up
+  [ENT]:     Set selection as parent of current task (or clear if already)
+  [ESC]:     Cancel
+Dependency mode:
+  [ESC/G]:   Exit dependency mode
+Trash mode:
+  [j/k]:     Move selection down/up
+  [ENT]:     Restore selected task
+  [ESC]:     Exit trash mode
+Bulk mode:
+  [j/k]:     Move selection down/up
+  [ENT]:     Choose command, then its priority, context, project or file
+  [ESC]:     Cancel
 Help mode:
   [ESC/SPC]: Exit help mode
+Report mode:
+  [ESC/R]:   Exit report mode
+Time report mode:
+  [d/w/m]:   Report time spent per day/week/month
+  [h/l]:     Move to previous/next period
+  [ESC/Y]:   Exit time report mode
 ";
 
 /// Run the application.  Setup terminal, run the application loop, then cleanup
@@ -44,8 +118,11 @@ pub fn run(
     let backend = ratatui::backend::CrosstermBackend::new(stdout);
     let mut terminal = ratatui::Terminal::new(backend)?;
 
-    // Run application logic
-    let res = run_app(&mut terminal, app, ui_state);
+    // Run application logic on terminal input, ticks and file changes
+    let mut events = event::Events::new(TICK_RATE);
+    let sources = app.sources().iter().map(|path| path.to_string()).collect();
+    event::watch_sources(sources, events.sender(), WATCH_INTERVAL);
+    let res = run_app(&mut terminal, app, ui_state, &mut events);
 
     // restore terminal
     crossterm::terminal::disable_raw_mode()?;
@@ -57,19 +134,101 @@ pub fn run(
 
     terminal.show_cursor()?;
 
-    // Report any errors and exit run
-    if let Err(err) = res {
-        println!("{:?}", err);
+    // Report any errors of the application loop once the terminal is restored
+    Ok(res?)
+}
+
+/// Suspend the terminal UI and edit `text` in a temporary file with the
+/// user's `$VISUAL` or `$EDITOR` (default vi), returning the edited text once
+/// the editor exits.  The terminal UI is restored even if editing fails.
+fn edit_externally<B: ratatui::backend::Backend>(
+    terminal: &mut ratatui::Terminal<B>,
+    text: &str,
+) -> std::io::Result<String> {
+    let path = {
+        use std::io::Write;
+        let (path, mut file) = create_temp_file()?;
+        file.write_all(text.as_bytes())?;
+        path
+    };
+
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::event::DisableMouseCapture
+    )?;
+
+    let editor = std::env::var("VISUAL")
+        .or(std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let mut words = editor.split_whitespace();
+    let status = std::process::Command::new(words.next().unwrap_or("vi"))
+        .args(words)
+        .arg(&path)
+        .status();
+
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::event::EnableMouseCapture
+    )?;
+    terminal.clear()?;
+
+    let result = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path),
+        Ok(status) => Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("'{}' exited with {}", editor, status),
+        )),
+        Err(err) => Err(err),
+    };
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Create a new file only readable by the user in the temporary directory.
+/// The file is created exclusively, so an existing file or symlink planted
+/// at the path is never followed, and another name is tried instead.
+fn create_temp_file() -> std::io::Result<(std::path::PathBuf, std::fs::File)> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut attempt = 0;
+    loop {
+        let name = format!("kdo-{}-{:x}-{}.txt", std::process::id(), nanos, attempt);
+        let path = std::env::temp_dir().join(name);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
     }
-    Ok(())
 }
 
-/// The main application loop. Checks for user input, updates application state,
-/// then draws application UI to terminal.
-fn run_app<B: ratatui::backend::Backend>(
+/// How often the app is sent [app::AppEvent::Tick], redrawing timers and the
+/// clock
+const TICK_RATE: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How often the todo.txt files are checked for changes by other programs
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The main application loop. Waits for user input or app events, updates
+/// application state, then draws application UI to terminal.  Returns once
+/// the app quits or `events` fails, eg, at the end of [event::ScriptedEvents].
+pub fn run_app<B: ratatui::backend::Backend, E: EventSource>(
     terminal: &mut ratatui::Terminal<B>,
     app: &mut app::App,
     ui_state: &mut state::State,
+    events: &mut E,
 ) -> std::io::Result<()> {
     loop {
         app.start_frame();
@@ -79,8 +238,24 @@ fn run_app<B: ratatui::backend::Backend>(
         terminal.draw(|f| draw::draw(f, app, ui_state))?;
         let mut elapsed = draw_start.elapsed().unwrap().as_secs_f64();
 
-        if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
+        let key = match events.wait()? {
+            event::Event::Input(crossterm::event::Event::Key(key)) => Some(key),
+            event::Event::Input(_) => None,
+            event::Event::App(app_event) => {
+                // Ring the terminal bell, eg, when a pomodoro period ends
+                if app.handle_event(app_event) {
+                    use std::io::Write;
+                    let mut stdout = std::io::stdout();
+                    stdout.write_all(b"\x07")?;
+                    stdout.flush()?;
+                }
+                None
+            }
+        };
+
+        if let Some(key) = key {
             let handle_input_start = std::time::SystemTime::now();
+            app.clear_error();
             match app.mode() {
                 app::Mode::Normal => {
                     match key.code {
@@ -105,7 +280,7 @@ fn run_app<B: ratatui::backend::Backend>(
                         }
                         crossterm::event::KeyCode::Char('e') | crossterm::event::KeyCode::Enter => {
                             let input_string = app.enter_edit_mode();
-                            ui_state.input = tui_input::Input::new(input_string);
+                            ui_state.set_input(input_string);
                         }
                         crossterm::event::KeyCode::Char('s') => {
                             app.enter_confirm_mode(app::ConfirmedAction::Save);
@@ -119,20 +294,121 @@ fn run_app<B: ratatui::backend::Backend>(
                         crossterm::event::KeyCode::Char('X') => {
                             app.toggle_task_complete();
                         }
+                        crossterm::event::KeyCode::Char('z') => {
+                            app.toggle_collapse();
+                        }
+                        crossterm::event::KeyCode::Char('R') => {
+                            app.enter_report_mode();
+                        }
+                        crossterm::event::KeyCode::Char('r') => {
+                            app.toggle_timer();
+                        }
+                        crossterm::event::KeyCode::Char('o') => {
+                            app.toggle_pomodoro();
+                        }
+                        crossterm::event::KeyCode::Char('Y') => {
+                            app.enter_time_report_mode();
+                        }
+                        crossterm::event::KeyCode::Char('i') => {
+                            app.toggle_detail();
+                        }
+                        crossterm::event::KeyCode::Char('f') => {
+                            app.enter_detail_mode();
+                        }
+                        crossterm::event::KeyCode::Char('P') => {
+                            app.enter_priority_mode();
+                        }
+                        crossterm::event::KeyCode::Char('+') => {
+                            app.raise_priority();
+                        }
+                        crossterm::event::KeyCode::Char('-') => {
+                            app.lower_priority();
+                        }
+                        crossterm::event::KeyCode::Char('t') => {
+                            app.enter_date_picker();
+                        }
+                        crossterm::event::KeyCode::Char('c') => {
+                            app.enter_pick_mode(app::PickKind::Context);
+                        }
+                        crossterm::event::KeyCode::Char('p') => {
+                            app.enter_pick_mode(app::PickKind::Project);
+                        }
+                        crossterm::event::KeyCode::Char('m') => {
+                            app.toggle_mark();
+                        }
+                        crossterm::event::KeyCode::Char('M') => {
+                            app.mark_all();
+                        }
+                        crossterm::event::KeyCode::Char('*') => {
+                            app.invert_marks();
+                        }
+                        crossterm::event::KeyCode::Char('b') => {
+                            app.enter_bulk_mode();
+                        }
+                        crossterm::event::KeyCode::Char('u') => {
+                            app.undo();
+                        }
+                        crossterm::event::KeyCode::Char('D') => {
+                            app.enter_confirm_mode(app::ConfirmedAction::Delete);
+                        }
+                        crossterm::event::KeyCode::Char('T') => {
+                            app.enter_trash_mode();
+                        }
+                        crossterm::event::KeyCode::Char('a') => {
+                            app.enter_capture_mode();
+                            ui_state.set_input(String::new());
+                        }
+                        crossterm::event::KeyCode::Char('I') => {
+                            app.toggle_inbox();
+                        }
+                        crossterm::event::KeyCode::Char('L') => {
+                            app.enter_link_mode();
+                        }
+                        crossterm::event::KeyCode::Char('G') => {
+                            app.enter_dependencies_mode();
+                        }
+                        crossterm::event::KeyCode::Char('B') => {
+                            app.toggle_hide_blocked();
+                        }
+                        crossterm::event::KeyCode::Char('O') => {
+                            app.toggle_outline();
+                        }
+                        crossterm::event::KeyCode::Char('>') => {
+                            app.enter_parent_mode();
+                        }
+                        crossterm::event::KeyCode::Char('E') => {
+                            let text = app.enter_external_edit(false);
+                            app.exit_external_edit(edit_externally(terminal, &text));
+                        }
+                        crossterm::event::KeyCode::Char('V') => {
+                            let text = app.enter_external_edit(true);
+                            app.exit_external_edit(edit_externally(terminal, &text));
+                        }
                         _ => {}
                     }
                 }
                 app::Mode::Edit => match key.code {
+                    crossterm::event::KeyCode::Esc if ui_state.completion.is_some() => {
+                        ui_state.completion = None;
+                    }
                     crossterm::event::KeyCode::Esc => {
                         app.exit_edit_mode(None);
                     }
                     crossterm::event::KeyCode::Enter => {
+                        ui_state.completion = None;
                         app.exit_edit_mode(Some(ui_state.input.value().to_string()));
                     }
+                    crossterm::event::KeyCode::Tab => {
+                        ui_state.cycle_completion(true);
+                    }
+                    crossterm::event::KeyCode::BackTab => {
+                        ui_state.cycle_completion(false);
+                    }
                     _ => {
                         ui_state
                             .input
                             .handle_event(&crossterm::event::Event::Key(key));
+                        ui_state.update_completion(app);
                     }
                 },
                 app::Mode::Help => match key.code {
@@ -141,12 +417,188 @@ fn run_app<B: ratatui::backend::Backend>(
                     }
                     _ => {}
                 },
+                app::Mode::Detail => match key.code {
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('f') => {
+                        app.exit_detail_mode();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Char('e') | crossterm::event::KeyCode::Enter => {
+                        if let Some(input_string) = app.enter_field_edit_mode() {
+                            ui_state.set_input(input_string);
+                        }
+                    }
+                    _ => {}
+                },
+                app::Mode::Priority => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.cancel_action();
+                    }
+                    crossterm::event::KeyCode::Char(' ') | crossterm::event::KeyCode::Backspace => {
+                        app.set_priority(None);
+                    }
+                    crossterm::event::KeyCode::Char(c) => {
+                        app.set_priority(Some(c));
+                    }
+                    _ => {}
+                },
+                app::Mode::DatePicker => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.cancel_action();
+                    }
+                    crossterm::event::KeyCode::Char('h') | crossterm::event::KeyCode::Left => {
+                        app.navigate_left();
+                    }
+                    crossterm::event::KeyCode::Char('l') | crossterm::event::KeyCode::Right => {
+                        app.navigate_right();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Char('H') => {
+                        app.move_picker_month(-1);
+                    }
+                    crossterm::event::KeyCode::Char('L') => {
+                        app.move_picker_month(1);
+                    }
+                    crossterm::event::KeyCode::Char('t') => {
+                        app.move_picker_date((app.today() - app.picker_date()).num_days());
+                    }
+                    crossterm::event::KeyCode::Enter => {
+                        app.exit_date_picker(true);
+                    }
+                    crossterm::event::KeyCode::Backspace | crossterm::event::KeyCode::Delete => {
+                        app.exit_date_picker(false);
+                    }
+                    _ => {}
+                },
+                app::Mode::Pick(_) => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.exit_pick_mode();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Char(' ') | crossterm::event::KeyCode::Enter => {
+                        app.toggle_picked();
+                    }
+                    _ => {}
+                },
+                app::Mode::Link => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.exit_link_mode();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Char(' ') | crossterm::event::KeyCode::Enter => {
+                        app.toggle_link();
+                    }
+                    _ => {}
+                },
+                app::Mode::Parent => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.exit_parent_mode();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Enter => {
+                        app.choose_parent();
+                    }
+                    _ => {}
+                },
+                app::Mode::Dependencies => match key.code {
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('G') => {
+                        app.exit_dependencies_mode();
+                    }
+                    _ => {}
+                },
+                app::Mode::Trash => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.exit_trash_mode();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Enter => {
+                        app.restore_from_trash();
+                    }
+                    _ => {}
+                },
+                app::Mode::Bulk(command) => match key.code {
+                    crossterm::event::KeyCode::Esc => {
+                        app.cancel_action();
+                    }
+                    crossterm::event::KeyCode::Char('j') | crossterm::event::KeyCode::Down => {
+                        app.navigate_down();
+                    }
+                    crossterm::event::KeyCode::Char('k') | crossterm::event::KeyCode::Up => {
+                        app.navigate_up();
+                    }
+                    crossterm::event::KeyCode::Enter if command.is_none() => {
+                        app.select_bulk_command();
+                    }
+                    crossterm::event::KeyCode::Enter => {
+                        app.select_bulk_choice();
+                    }
+                    _ => {}
+                },
+                app::Mode::Report => match key.code {
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('R') => {
+                        app.exit_report_mode();
+                    }
+                    _ => {}
+                },
+                app::Mode::TimeReport => match key.code {
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('Y') => {
+                        app.exit_time_report_mode();
+                    }
+                    crossterm::event::KeyCode::Char('d') => {
+                        app.set_time_span(::app::timelog::TimeSpan::Day);
+                    }
+                    crossterm::event::KeyCode::Char('w') => {
+                        app.set_time_span(::app::timelog::TimeSpan::Week);
+                    }
+                    crossterm::event::KeyCode::Char('m') => {
+                        app.set_time_span(::app::timelog::TimeSpan::Month);
+                    }
+                    crossterm::event::KeyCode::Char('h') | crossterm::event::KeyCode::Left => {
+                        app.step_time_report(-1);
+                    }
+                    crossterm::event::KeyCode::Char('l') | crossterm::event::KeyCode::Right => {
+                        app.step_time_report(1);
+                    }
+                    _ => {}
+                },
                 app::Mode::Confirm(_) => match key.code {
                     crossterm::event::KeyCode::Esc
                     | crossterm::event::KeyCode::Char('N')
                     | crossterm::event::KeyCode::Char('n') => {
                         app.cancel_action();
                     }
+                    crossterm::event::KeyCode::Enter if !app.confirm_by_default() => {
+                        app.cancel_action();
+                    }
                     crossterm::event::KeyCode::Enter
                     | crossterm::event::KeyCode::Char('Y')
                     | crossterm::event::KeyCode::Char('y') => {
@@ -164,3 +616,137 @@ fn run_app<B: ratatui::backend::Backend>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyCode;
+
+    const TODO: &str = "\
+(A) 2023-11-01 call mom @phone +family
+2023-11-02 pay rent +home due:2023-12-01
+(B) write report @work +work.backend
+x 2023-11-30 2023-11-03 book flights @computer +travel";
+
+    /// Run the main loop on a few tasks until the end of `script`, returning
+    /// the app and the last frame drawn
+    fn run_script(
+        tasks: &[todo_txt::task::Task],
+        script: event::ScriptedEvents,
+    ) -> (app::App<'static>, String) {
+        let mut app = app::App::new("kdo", "todo.txt", tasks);
+        let mut ui_state = state::State::new();
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        let mut script = script;
+        let result = run_app(&mut terminal, &mut app, &mut ui_state, &mut script);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+
+        let buffer = terminal.backend().buffer();
+        let mut screen = String::new();
+        for y in 0..buffer.area.height {
+            let line: String = (0..buffer.area.width)
+                .map(|x| buffer.get(x, y).symbol.as_str())
+                .collect();
+            screen.push_str(line.trim_end());
+            screen.push('\n');
+        }
+        (app, screen)
+    }
+
+    fn tasks() -> Vec<todo_txt::task::Task> {
+        TODO.lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    /// Compare `screen` to the snapshot `name` in `snapshots/`, writing the
+    /// snapshot instead if `UPDATE_SNAPSHOTS` is set
+    fn assert_snapshot(name: &str, screen: &str) {
+        let path = format!("{}/snapshots/{}.txt", env!("CARGO_MANIFEST_DIR"), name);
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, screen).unwrap();
+        }
+        let snapshot = std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("no snapshot {}, run with UPDATE_SNAPSHOTS=1", path));
+        assert_eq!(screen, snapshot, "snapshot {} differs", name);
+    }
+
+    #[test]
+    fn main_screen() {
+        let (app, screen) = run_script(&tasks(), event::ScriptedEvents::typed("jj"));
+        assert!(app.mode() == app::Mode::Normal);
+        assert_snapshot("main", &screen);
+
+        // Filtering by the second context
+        let (_, screen) = run_script(&tasks(), event::ScriptedEvents::typed("lj"));
+        assert_snapshot("main_context", &screen);
+    }
+
+    #[test]
+    fn help_mode() {
+        let (app, screen) = run_script(&tasks(), event::ScriptedEvents::typed("H"));
+        assert!(app.mode() == app::Mode::Help);
+        assert_snapshot("help", &screen);
+
+        let (app, _) = run_script(&tasks(), event::ScriptedEvents::typed("H "));
+        assert!(app.mode() == app::Mode::Normal);
+    }
+
+    #[test]
+    fn edit_mode() {
+        let (app, screen) = run_script(&tasks(), event::ScriptedEvents::typed("je @h"));
+        assert!(app.mode() == app::Mode::Edit);
+        assert_snapshot("edit", &screen);
+
+        // Saving the edit
+        let mut script = event::ScriptedEvents::typed("je @home");
+        script.push(event::ScriptedEvents::keys(&[KeyCode::Enter]));
+        let (app, _) = run_script(&tasks(), script);
+        assert!(app.mode() == app::Mode::Normal);
+        assert_eq!(app.tasks()[1].contexts(), ["phone", "home"]);
+    }
+
+    #[test]
+    fn confirm_mode() {
+        let (app, screen) = run_script(&tasks(), event::ScriptedEvents::typed("jD"));
+        assert!(matches!(app.mode(), app::Mode::Confirm(_)));
+        assert_snapshot("confirm", &screen);
+
+        let (app, _) = run_script(&tasks(), event::ScriptedEvents::typed("jDn"));
+        assert_eq!(app.tasks().len(), 5);
+        let (app, _) = run_script(&tasks(), event::ScriptedEvents::typed("jDy"));
+        assert_eq!(app.tasks().len(), 4);
+    }
+
+    #[test]
+    fn temp_files() {
+        let (path, _) = create_temp_file().unwrap();
+        let (other, _) = create_temp_file().unwrap();
+        assert_ne!(path, other);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(other).unwrap();
+    }
+
+    #[test]
+    fn quit() {
+        let mut app = app::App::new("kdo", "todo.txt", &[]);
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        let mut script = event::ScriptedEvents::typed("q");
+        let result = run_app(
+            &mut terminal,
+            &mut app,
+            &mut state::State::new(),
+            &mut script,
+        );
+        assert!(result.is_ok());
+    }
+}